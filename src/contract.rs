@@ -8,9 +8,18 @@ mod state;
 #[path = "./contract_unit_tests.rs"]
 mod tests;
 
-use atoma_demo::{ChatInteraction, Operation, PublicKey};
+use std::{collections::BTreeSet, mem};
+
+use atoma_demo::{
+    ApiToken, AttestationPolicy, AttestationReport, AuditLogEntry, BlockTime, ChatFee,
+    ChatInteraction, ChatLogEntry, ChatLogRetention, EmbeddingRecord, ImageGenerationRecord,
+    LoggedChatInteraction, NodeAttestation, NodeMisbehaviorEvidence, NodeReceipt, NodeRewardClaim,
+    NodeSuspension, Operation, PrunedChatLogRange, PublicKey, RedactedChatInteraction, Signature,
+};
 use linera_sdk::{
-    linera_base_types::WithContractAbi,
+    linera_base_types::{
+        Account, AccountOwner, Amount, ChainId, CryptoHash, Timestamp, WithContractAbi,
+    },
     views::{RootView, View},
     Contract, ContractRuntime,
 };
@@ -46,16 +55,88 @@ impl Contract for ApplicationContract {
 
     async fn execute_operation(&mut self, operation: Self::Operation) -> Self::Response {
         match operation {
-            Operation::UpdateNodes { add, remove } => self.update_nodes(add, remove),
-            Operation::LogChatInteraction { interaction } => self.log_chat_interaction(interaction),
+            Operation::UpdateNodes { add, remove } => self.update_nodes(add, remove).await,
+            Operation::UpdateModels { add, remove } => self.update_models(add, remove),
+            Operation::ConfigureAuditLog { enabled } => self.configure_audit_log(enabled),
+            Operation::PruneChatLog {
+                older_than,
+                max_count,
+                keep_digest,
+            } => {
+                self.prune_chat_log(older_than, max_count, keep_digest)
+                    .await
+            }
+            Operation::RedactChatLogEntry { index } => self.redact_chat_log_entry(index).await,
+            Operation::ConfigureChatLogRetention { retention } => {
+                self.configure_chat_log_retention(retention)
+            }
+            Operation::UpdateApiTokens { add, remove } => self.update_api_tokens(add, remove),
+            Operation::ConfigureChatFee { fee } => self.configure_chat_fee(fee),
+            Operation::LogChatInteraction {
+                interaction,
+                payment,
+                receipts,
+                quorum,
+            } => self.log_chat_interaction(interaction, payment, receipts, quorum),
+            Operation::ReportNode { node, evidence } => self.report_node(node, evidence),
+            Operation::ClaimNodeRewards {
+                node,
+                destination,
+                signature,
+            } => self.claim_node_rewards(node, destination, signature),
+            Operation::ConfigureAttestation { policy } => self.configure_attestation(policy),
+            Operation::RegisterAttestation { report } => self.register_attestation(report),
+            Operation::LogEmbedding {
+                embedding,
+                receipts,
+                quorum,
+            } => self.log_embedding(embedding, receipts, quorum),
+            Operation::LogImageGeneration {
+                generation,
+                receipts,
+                quorum,
+            } => self.log_image_generation(generation, receipts, quorum),
         }
     }
 
     async fn execute_message(&mut self, message: Self::Message) {
         match message {
-            Message::VerifySignature(interaction) => self.verify_signature(interaction),
+            Message::VerifySignature {
+                interaction,
+                requested_at,
+                payment,
+                receipts,
+                quorum,
+            } => {
+                self.verify_signature(interaction, requested_at, payment, receipts, quorum)
+                    .await
+            }
+            Message::SuspendNode { node, evidence } => self.suspend_node(node, evidence).await,
+            Message::PayNodeRewards {
+                node,
+                destination,
+                signature,
+            } => self.pay_node_rewards(node, destination, signature).await,
+            Message::VerifyAttestation(report) => self.verify_attestation(report),
             Message::LogVerifiedChatInteraction(interaction) => {
-                self.log_verified_chat_interaction(interaction)
+                self.log_verified_chat_interaction(interaction).await
+            }
+            Message::VerifyEmbedding {
+                embedding,
+                receipts,
+                quorum,
+            } => self.verify_embedding(embedding, receipts, quorum).await,
+            Message::LogVerifiedEmbedding(embedding) => self.log_verified_embedding(embedding),
+            Message::VerifyImageGeneration {
+                generation,
+                receipts,
+                quorum,
+            } => {
+                self.verify_image_generation(generation, receipts, quorum)
+                    .await
+            }
+            Message::LogVerifiedImageGeneration(generation) => {
+                self.log_verified_image_generation(generation)
             }
         }
     }
@@ -66,31 +147,95 @@ impl Contract for ApplicationContract {
 }
 
 /// Cross-chain messages sent privately between the application shards.
-#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
 pub enum Message {
-    /// Request to verify a [`ChatInteraction`]'s signature.
-    VerifySignature(ChatInteraction),
+    /// Request to verify a [`ChatInteraction`]'s signature, with the `payment` transferred to
+    /// the application's account for the verification fee and the `receipts` of the nodes that
+    /// attest to it, of which at least `quorum` are required.
+    VerifySignature {
+        interaction: ChatInteraction,
+        requested_at: BlockTime,
+        payment: Amount,
+        receipts: Vec<NodeReceipt>,
+        quorum: u32,
+    },
+
+    /// Request to suspend an Atoma `node` if the `evidence` proves that it misbehaved.
+    SuspendNode {
+        node: PublicKey,
+        evidence: NodeMisbehaviorEvidence,
+    },
+
+    /// Request to pay out the rewards accumulated by an Atoma `node` to the `destination`
+    /// account designated by the node's `signature`.
+    PayNodeRewards {
+        node: PublicKey,
+        destination: Account,
+        signature: Signature,
+    },
 
-    /// Response indicating that the [`ChatInteraction`]'s signature was verified and approved.
-    LogVerifiedChatInteraction(ChatInteraction),
+    /// Request to verify an [`AttestationReport`] and register the attestation of its node.
+    VerifyAttestation(AttestationReport),
+
+    /// Response indicating that the [`ChatInteraction`]'s signature was verified and approved,
+    /// attributed to the requester of the verification.
+    LogVerifiedChatInteraction(LoggedChatInteraction),
+
+    /// Request to verify an [`EmbeddingRecord`], attested by the node `receipts`, of which at
+    /// least `quorum` are required.
+    VerifyEmbedding {
+        embedding: EmbeddingRecord,
+        receipts: Vec<NodeReceipt>,
+        quorum: u32,
+    },
+
+    /// Response indicating that the [`EmbeddingRecord`] was verified and approved.
+    LogVerifiedEmbedding(EmbeddingRecord),
+
+    /// Request to verify an [`ImageGenerationRecord`], attested by the node `receipts`, of which
+    /// at least `quorum` are required.
+    VerifyImageGeneration {
+        generation: ImageGenerationRecord,
+        receipts: Vec<NodeReceipt>,
+        quorum: u32,
+    },
+
+    /// Response indicating that the [`ImageGenerationRecord`] was verified and approved.
+    LogVerifiedImageGeneration(ImageGenerationRecord),
 }
 
 impl ApplicationContract {
     /// Handles an [`Operation::UpdateNodes`] by adding the `nodes_to_add` and removing the
     /// `nodes_to_remove`.
-    fn update_nodes(&mut self, nodes_to_add: Vec<PublicKey>, nodes_to_remove: Vec<PublicKey>) {
-        assert!(
-            self.runtime.chain_id() == self.runtime.application_creator_chain_id(),
-            "Only the chain that created the application can manage the set of active nodes"
-        );
+    ///
+    /// The statistics of each node record since when it's active, which is kept if an active node
+    /// is added again.
+    async fn update_nodes(
+        &mut self,
+        nodes_to_add: Vec<PublicKey>,
+        nodes_to_remove: Vec<PublicKey>,
+    ) {
+        self.assert_creation_chain("manage the set of active nodes");
+
+        Self::assert_sets_are_disjoint(&nodes_to_add, &nodes_to_remove);
 
-        Self::assert_key_sets_are_disjoint(&nodes_to_add, &nodes_to_remove);
+        let now = self.runtime.system_time();
 
         for node in nodes_to_remove {
             self.state
                 .active_atoma_nodes
                 .remove(&node)
                 .expect("Failed to remove a node from the set of active Atoma nodes");
+
+            if let Some(stats) = self
+                .state
+                .node_stats
+                .get_mut(&node)
+                .await
+                .expect("Failed to read the statistics of an Atoma node")
+            {
+                stats.active_since = None;
+            }
         }
 
         for node in nodes_to_add {
@@ -98,55 +243,882 @@ impl ApplicationContract {
                 .active_atoma_nodes
                 .insert(&node)
                 .expect("Failed to add a node to the set of active Atoma nodes");
+
+            let stats = self
+                .state
+                .node_stats
+                .get_mut_or_default(&node)
+                .await
+                .expect("Failed to read the statistics of an Atoma node");
+
+            stats.active_since.get_or_insert(now);
         }
     }
 
-    /// Checks if two sets of [`PublicKey`]s are disjoint.
-    fn assert_key_sets_are_disjoint(left: &[PublicKey], right: &[PublicKey]) {
+    /// Handles an [`Operation::UpdateModels`] by adding the `models_to_add` and removing the
+    /// `models_to_remove` from the allowed models.
+    fn update_models(&mut self, models_to_add: Vec<String>, models_to_remove: Vec<String>) {
+        self.assert_creation_chain("manage the set of allowed models");
+
+        Self::assert_sets_are_disjoint(&models_to_add, &models_to_remove);
+
+        for model in models_to_remove {
+            self.state
+                .allowed_models
+                .remove(&model)
+                .expect("Failed to remove a model from the set of allowed models");
+        }
+
+        for model in models_to_add {
+            self.state
+                .allowed_models
+                .insert(&model)
+                .expect("Failed to add a model to the set of allowed models");
+        }
+    }
+
+    /// Handles an [`Operation::ConfigureAuditLog`] by enabling or disabling the audit log of
+    /// verified chat interactions.
+    fn configure_audit_log(&mut self, enabled: bool) {
+        self.assert_creation_chain("configure the audit log");
+
+        self.state.is_audit_log_enabled.set(enabled);
+    }
+
+    /// Handles an [`Operation::ConfigureChatFee`] by replacing the fee charged for verifying
+    /// each chat interaction.
+    fn configure_chat_fee(&mut self, fee: Option<ChatFee>) {
+        self.assert_creation_chain("configure the chat fee");
+
+        if let Some(fee) = fee {
+            assert!(
+                fee.node_reward <= fee.amount,
+                "Node reward can't exceed the chat fee"
+            );
+        }
+
+        self.state.chat_fee.set(fee);
+    }
+
+    /// Handles an [`Operation::ConfigureAttestation`] by replacing the policy used to verify
+    /// the attestations of Atoma nodes.
+    ///
+    /// Registered attestations are kept, but are only trusted while their attester and enclave
+    /// measurement are trusted by the current policy.
+    fn configure_attestation(&mut self, policy: Option<AttestationPolicy>) {
+        self.assert_creation_chain("configure node attestation");
+
+        self.state.attestation_policy.set(policy);
+    }
+
+    /// Handles an [`Operation::PruneChatLog`] by removing the oldest entries of the chat log
+    /// while they were requested before `older_than` or while there are more than `max_count`
+    /// entries.
+    ///
+    /// Only the chain's owners are allowed to prune the chat log.
+    async fn prune_chat_log(
+        &mut self,
+        older_than: Option<Timestamp>,
+        max_count: Option<u64>,
+        keep_digest: bool,
+    ) {
+        self.assert_chain_owner("prune the chat log");
+
+        self.prune_chat_log_entries(older_than, max_count, keep_digest)
+            .await;
+    }
+
+    /// Removes the oldest entries of the chat log while they were requested before `older_than`
+    /// or while there are more than `max_count` entries.
+    ///
+    /// Only a prefix of the chat log is pruned, so that the indices of the remaining entries
+    /// don't change. The Merkle mountain range still covers the pruned entries, and if
+    /// `keep_digest` is set, a [`PrunedChatLogRange`] summarizing them is also kept.
+    async fn prune_chat_log_entries(
+        &mut self,
+        older_than: Option<Timestamp>,
+        max_count: Option<u64>,
+        keep_digest: bool,
+    ) {
+        let first_index = *self.state.chat_log_pruned_count.get();
+        let log_length = self.state.chat_log_merkle_range.get().len();
+        let mut pruned_entries = Vec::new();
+
+        for index in first_index..log_length {
+            let entry = self
+                .state
+                .chat_log
+                .get(&index)
+                .await
+                .expect("Failed to read the oldest entry of the chat log")
+                .expect("Chat log entry that wasn't pruned is missing");
+
+            let is_too_old =
+                older_than.is_some_and(|cutoff| entry.requested_at().timestamp < cutoff);
+            let is_in_excess = max_count.is_some_and(|max_count| log_length - index > max_count);
+
+            if !is_too_old && !is_in_excess {
+                break;
+            }
+
+            self.state
+                .chat_log
+                .remove(&index)
+                .expect("Failed to remove the oldest entry of the chat log");
+            pruned_entries.push(entry);
+        }
+
+        if pruned_entries.is_empty() {
+            return;
+        }
+
+        let pruned_count = self.state.chat_log_pruned_count.get_mut();
+
+        if keep_digest {
+            self.state
+                .chat_log_pruned_ranges
+                .push(PrunedChatLogRange::new(*pruned_count, &pruned_entries));
+        }
+
+        *pruned_count += pruned_entries.len() as u64;
+    }
+
+    /// Handles an [`Operation::RedactChatLogEntry`] by replacing the chat log entry at `index`
+    /// with a [`RedactedChatInteraction`] tombstone.
+    ///
+    /// Only the chain's owners are allowed to redact entries.
+    async fn redact_chat_log_entry(&mut self, index: u64) {
+        self.assert_chain_owner("redact chat log entries");
+
+        assert!(
+            index >= *self.state.chat_log_pruned_count.get(),
+            "Chat log entry to redact was already pruned"
+        );
+        let redacted_at = self.block_time();
+
+        let entry = self
+            .state
+            .chat_log
+            .get_mut(&index)
+            .await
+            .expect("Failed to read the chat log entry to redact")
+            .expect("Chat log entry to redact does not exist");
+
+        let ChatLogEntry::Logged(logged_interaction) = entry else {
+            panic!("Chat log entry was already redacted");
+        };
+
+        *entry = ChatLogEntry::Redacted(RedactedChatInteraction::new(
+            logged_interaction,
+            redacted_at,
+        ));
+    }
+
+    /// Handles an [`Operation::ConfigureChatLogRetention`] by replacing the chat log's
+    /// retention policy.
+    ///
+    /// Only the chain's owners are allowed to configure the retention policy.
+    fn configure_chat_log_retention(&mut self, retention: Option<ChatLogRetention>) {
+        self.assert_chain_owner("configure the chat log's retention");
+
+        self.state.chat_log_retention.set(retention);
+    }
+
+    /// Handles an [`Operation::UpdateApiTokens`] by storing the `tokens_to_add` and removing the
+    /// tokens named in `tokens_to_remove`.
+    ///
+    /// Only the chain's owners are allowed to manage the chain's API tokens.
+    fn update_api_tokens(&mut self, tokens_to_add: Vec<ApiToken>, tokens_to_remove: Vec<String>) {
+        self.assert_chain_owner("update the API tokens");
+
+        let names_to_add = tokens_to_add
+            .iter()
+            .map(|api_token| api_token.name.clone())
+            .collect::<Vec<_>>();
+
+        Self::assert_sets_are_disjoint(&names_to_add, &tokens_to_remove);
+
+        for name in tokens_to_remove {
+            self.state
+                .api_tokens
+                .remove(&name)
+                .expect("Failed to remove an API token");
+        }
+
+        for api_token in tokens_to_add {
+            self.state
+                .api_tokens
+                .insert(&api_token.name.clone(), api_token)
+                .expect("Failed to store an API token");
+        }
+    }
+
+    /// Checks if the contract is executing on the chain that created the application, which is
+    /// the only one allowed to perform the described `action`.
+    fn assert_creation_chain(&mut self, action: &str) {
+        assert!(
+            self.runtime.chain_id() == self.runtime.application_creator_chain_id(),
+            "Only the chain that created the application can {action}"
+        );
+    }
+
+    /// Checks if the block was signed by one of the chain's owners, who are the only ones allowed
+    /// to perform the described `action`.
+    fn assert_chain_owner(&mut self, action: &str) {
+        let signer = self
+            .runtime
+            .authenticated_signer()
+            .unwrap_or_else(|| panic!("Only the chain's owners can {action}"));
+
+        assert!(
+            self.runtime
+                .chain_ownership()
+                .all_owners()
+                .any(|owner| *owner == signer),
+            "Only the chain's owners can {action}"
+        );
+    }
+
+    /// Checks if two sets of elements to add and to remove are disjoint.
+    fn assert_sets_are_disjoint<T: PartialEq>(left: &[T], right: &[T]) {
         let (smallest_set, largest_set) = if left.len() < right.len() {
             (left, right)
         } else {
             (right, left)
         };
 
-        let disjoint = largest_set.iter().all(|key| !smallest_set.contains(key));
+        let disjoint = largest_set
+            .iter()
+            .all(|element| !smallest_set.contains(element));
 
         assert!(
             disjoint,
-            "Conflicting request to add and remove the same node"
+            "Conflicting request to add and remove the same element"
         );
     }
 
     /// Handles an [`Operation::LogChatInteraction`] by requesting the [`ChatInteraction`]'s
     /// signature to be verified.
-    fn log_chat_interaction(&mut self, interaction: ChatInteraction) {
+    ///
+    /// The request is authenticated, so that the interaction can be attributed to the block's
+    /// signer, and it includes the time of the block that requested it.
+    ///
+    /// The `payment` is transferred from the chain's balance to the application's account on the
+    /// creation chain before the request is sent, so that it is credited before the request is
+    /// handled. If the request is rejected, the transfer bounces back and the payment is
+    /// refunded.
+    fn log_chat_interaction(
+        &mut self,
+        interaction: ChatInteraction,
+        payment: Amount,
+        receipts: Vec<NodeReceipt>,
+        quorum: u32,
+    ) {
         let creation_chain_id = self.runtime.application_creator_chain_id();
+        let requested_at = self.block_time();
+
+        if payment > Amount::ZERO {
+            let application_account = Account::new(creation_chain_id, self.application_owner());
+
+            self.runtime
+                .transfer(AccountOwner::CHAIN, application_account, payment);
+        }
 
         self.runtime
-            .send_message(creation_chain_id, Message::VerifySignature(interaction));
+            .prepare_message(Message::VerifySignature {
+                interaction,
+                requested_at,
+                payment,
+                receipts,
+                quorum,
+            })
+            .with_authentication()
+            .send_to(creation_chain_id);
     }
 
     /// Handles a [`Message::VerifySignature`] by verifying the signature and if accepted,
     /// responding with a [`Message::LogVerifiedChatInteraction`].
-    fn verify_signature(&mut self, interaction: ChatInteraction) {
-        let requester_chain_id = self
-            .runtime
-            .message_id()
-            .expect(
-                "`verify_signature` should only be called \
-                when handling a `Message::VerifySignature`",
-            )
-            .chain_id;
+    ///
+    /// Interactions with models that aren't in the set of allowed models are rejected. If they
+    /// have valid node `receipts`, the rejection is counted against the nodes, and the `payment`
+    /// is refunded explicitly instead of bouncing, so that the counts are kept. Approved
+    /// interactions are attributed to the chain that requested the verification and the signer
+    /// that authenticated the request, and are stamped with the time of the verification. If the
+    /// audit log is enabled, they are also recorded in it.
+    ///
+    /// The chat fee is charged from the `payment`, and requests that don't pay enough are
+    /// rejected.
+    ///
+    /// Each of the node `receipts` must be signed by a different active Atoma node that isn't
+    /// suspended, and there must be at least `quorum` of them. The nodes are then credited with
+    /// the interaction and rewarded from the chat fee.
+    async fn verify_signature(
+        &mut self,
+        interaction: ChatInteraction,
+        requested_at: BlockTime,
+        payment: Amount,
+        receipts: Vec<NodeReceipt>,
+        quorum: u32,
+    ) {
+        let is_allowed_model = self.is_allowed_model(&interaction.model).await;
+
+        self.assert_node_quorum(
+            &receipts,
+            interaction.id(),
+            interaction.choice_index,
+            quorum,
+        )
+        .await;
+
+        let requester_chain_id = self.requester_chain_id();
+
+        if !is_allowed_model {
+            assert!(
+                !receipts.is_empty(),
+                "Chat interaction was produced by a model that is not allowed"
+            );
+
+            for receipt in &receipts {
+                self.reject_node_interaction(receipt.node).await;
+            }
+
+            self.refund_payment(requester_chain_id, payment);
+            return;
+        }
+
+        let node_reward = self.charge_chat_fee(requester_chain_id, payment, receipts.len());
+
+        for receipt in receipts {
+            self.reward_node(receipt, node_reward).await;
+        }
+
+        let logged_interaction = LoggedChatInteraction {
+            interaction,
+            requester: self.runtime.authenticated_signer(),
+            requester_chain_id,
+            requested_at,
+            verified_at: self.block_time(),
+        };
+
+        if *self.state.is_audit_log_enabled.get() {
+            self.state
+                .audit_log
+                .push(AuditLogEntry::from(&logged_interaction));
+        }
+
+        self.runtime.send_message(
+            requester_chain_id,
+            Message::LogVerifiedChatInteraction(logged_interaction),
+        );
+    }
+
+    /// Transfers the chat fee from the `payment` held in the application's account to the
+    /// treasury, and refunds the rest of the `payment` to the chain with `requester_chain_id`.
+    ///
+    /// If the interaction has `receipt_count` node receipts, the fee's node reward is split evenly
+    /// between them and kept in the application's account as the fee pool. The reward for each
+    /// node is returned so that it can be credited to the nodes, and what's left over from the
+    /// split goes to the treasury.
+    ///
+    /// Panics if the `payment` doesn't cover the fee, so that the request is rejected and the
+    /// payment bounces back to the requester chain.
+    fn charge_chat_fee(
+        &mut self,
+        requester_chain_id: ChainId,
+        payment: Amount,
+        receipt_count: usize,
+    ) -> Amount {
+        let mut refund = payment;
+        let mut node_reward = Amount::ZERO;
+
+        if let Some(fee) = *self.state.chat_fee.get() {
+            refund = payment
+                .try_sub(fee.amount)
+                .expect("Payment does not cover the chat interaction fee");
+
+            if receipt_count > 0 {
+                node_reward =
+                    Amount::from_attos(u128::from(fee.node_reward) / receipt_count as u128);
+            }
+
+            let treasury_fee = fee
+                .amount
+                .try_sub(node_reward.saturating_mul(receipt_count as u128))
+                .expect("Node rewards should not exceed the chat fee");
+
+            if treasury_fee > Amount::ZERO {
+                let application_owner = self.application_owner();
+                self.runtime
+                    .transfer(application_owner, fee.treasury, treasury_fee);
+            }
+        }
+
+        self.refund_payment(requester_chain_id, refund);
+
+        node_reward
+    }
+
+    /// Refunds the `amount` of a payment held in the application's account to the chain with
+    /// `requester_chain_id`.
+    fn refund_payment(&mut self, requester_chain_id: ChainId, amount: Amount) {
+        if amount > Amount::ZERO {
+            let application_owner = self.application_owner();
+            self.runtime.transfer(
+                application_owner,
+                Account::chain(requester_chain_id),
+                amount,
+            );
+        }
+    }
+
+    /// Checks that the `receipts` were signed for the logged record with the `record_id`, which
+    /// holds the choice at `choice_index` of the request's responses, by at least `quorum`
+    /// different active Atoma nodes.
+    ///
+    /// If the attestation policy requires attested nodes, the record must be attested by at
+    /// least one node, so that it can't bypass the attestation checks with an empty quorum.
+    async fn assert_node_quorum(
+        &mut self,
+        receipts: &[NodeReceipt],
+        record_id: CryptoHash,
+        choice_index: u32,
+        quorum: u32,
+    ) {
+        if self.is_attestation_required() {
+            assert!(
+                quorum > 0,
+                "Response must be attested by a quorum of at least one node"
+            );
+        }
+
+        let mut nodes = BTreeSet::new();
+
+        for receipt in receipts {
+            assert!(
+                nodes.insert(receipt.node),
+                "Response has multiple receipts from the same node"
+            );
+
+            self.assert_valid_node_receipt(receipt, record_id, choice_index)
+                .await;
+        }
+
+        assert!(
+            nodes.len() >= quorum as usize,
+            "Response is not attested by a quorum of nodes"
+        );
+    }
+
+    /// Checks that the `receipt` was signed for the logged record with the `record_id`, which
+    /// holds the choice at `choice_index` of the request's responses, by an active Atoma node.
+    async fn assert_valid_node_receipt(
+        &mut self,
+        receipt: &NodeReceipt,
+        record_id: CryptoHash,
+        choice_index: u32,
+    ) {
+        let is_active_node = self
+            .state
+            .active_atoma_nodes
+            .contains(&receipt.node)
+            .await
+            .expect("Failed to read the set of active Atoma nodes");
+
+        assert!(
+            is_active_node,
+            "Response was produced by a node that is not active"
+        );
+        assert!(
+            !self.is_suspended_node(receipt.node).await,
+            "Response was produced by a suspended node"
+        );
+        assert!(
+            receipt.verify(record_id, choice_index),
+            "Node receipt has an invalid signature"
+        );
+
+        if self.is_attestation_required() {
+            assert!(
+                self.is_attested_node(receipt.node).await,
+                "Response was produced by a node without a valid attestation"
+            );
+        }
+    }
+
+    /// Checks if the attestation policy only accepts responses from attested nodes.
+    fn is_attestation_required(&self) -> bool {
+        self.state
+            .attestation_policy
+            .get()
+            .as_ref()
+            .is_some_and(|policy| policy.is_required)
+    }
+
+    /// Checks if the `node` has an attestation that is trusted by the current attestation
+    /// policy.
+    async fn is_attested_node(&mut self, node: PublicKey) -> bool {
+        let Some(policy) = self.state.attestation_policy.get() else {
+            return false;
+        };
+
+        self.state
+            .attested_atoma_nodes
+            .get(&node)
+            .await
+            .expect("Failed to read the attestations of Atoma nodes")
+            .is_some_and(|attestation| policy.trusts(&attestation.attester, &attestation.enclave))
+    }
+
+    /// Handles an [`Operation::RegisterAttestation`] by requesting the chain that created the
+    /// application to verify the attestation `report`.
+    fn register_attestation(&mut self, report: AttestationReport) {
+        let creation_chain_id = self.runtime.application_creator_chain_id();
+
+        self.runtime
+            .send_message(creation_chain_id, Message::VerifyAttestation(report));
+    }
+
+    /// Handles a [`Message::VerifyAttestation`] by registering the attestation of the report's
+    /// node if the `report` is valid according to the attestation policy.
+    ///
+    /// A new attestation replaces any previous attestation of the same node.
+    fn verify_attestation(&mut self, report: AttestationReport) {
+        let policy = self
+            .state
+            .attestation_policy
+            .get()
+            .as_ref()
+            .expect("Node attestation is not configured");
+
+        assert!(
+            report.verify(policy),
+            "Attestation report is not trusted by the attestation policy"
+        );
+
+        let attestation = NodeAttestation {
+            enclave: report.enclave,
+            attester: report.attester,
+            attested_at: self.block_time(),
+        };
+
+        self.state
+            .attested_atoma_nodes
+            .insert(&report.node, attestation)
+            .expect("Failed to register the attestation of an Atoma node");
+    }
+
+    /// Credits the node that signed the `receipt` with a verified chat interaction, the tokens it
+    /// served, and the `reward` kept for it in the fee pool.
+    async fn reward_node(&mut self, receipt: NodeReceipt, reward: Amount) {
+        let stats = self
+            .state
+            .node_stats
+            .get_mut_or_default(&receipt.node)
+            .await
+            .expect("Failed to read the statistics of an Atoma node");
+
+        stats.verified_interactions += 1;
+        stats.tokens_served = stats.tokens_served.saturating_add(receipt.tokens);
+        stats.unclaimed_rewards.saturating_add_assign(reward);
+    }
+
+    /// Handles an [`Operation::ReportNode`] by requesting the chain that created the application
+    /// to suspend the `node`.
+    fn report_node(&mut self, node: PublicKey, evidence: NodeMisbehaviorEvidence) {
+        let creation_chain_id = self.runtime.application_creator_chain_id();
+
+        self.runtime
+            .send_message(creation_chain_id, Message::SuspendNode { node, evidence });
+    }
+
+    /// Handles a [`Message::SuspendNode`] by suspending the `node` if the `evidence` proves that
+    /// it misbehaved.
+    ///
+    /// Every proven report is counted in the node's statistics, but nodes that are already
+    /// suspended keep their original suspension.
+    async fn suspend_node(&mut self, node: PublicKey, evidence: NodeMisbehaviorEvidence) {
+        assert!(
+            evidence.proves_misbehavior_of(node),
+            "Evidence does not prove that the node misbehaved"
+        );
+
+        self.state
+            .node_stats
+            .get_mut_or_default(&node)
+            .await
+            .expect("Failed to read the statistics of an Atoma node")
+            .reports += 1;
+
+        if self.is_suspended_node(node).await {
+            return;
+        }
+
+        let suspension = NodeSuspension {
+            reason: evidence.reason(),
+            suspended_at: self.block_time(),
+        };
+
+        self.state
+            .suspended_atoma_nodes
+            .insert(&node, suspension)
+            .expect("Failed to suspend an Atoma node");
+    }
+
+    /// Checks if the `model` is allowed to produce logged responses.
+    async fn is_allowed_model(&mut self, model: &str) -> bool {
+        self.state
+            .allowed_models
+            .contains(model)
+            .await
+            .expect("Failed to read the set of allowed models")
+    }
+
+    /// Checks if the `node` was suspended.
+    async fn is_suspended_node(&mut self, node: PublicKey) -> bool {
+        self.state
+            .suspended_atoma_nodes
+            .contains_key(&node)
+            .await
+            .expect("Failed to read the set of suspended Atoma nodes")
+    }
+
+    /// Counts a rejected chat interaction produced by the `node`.
+    async fn reject_node_interaction(&mut self, node: PublicKey) {
+        let stats = self
+            .state
+            .node_stats
+            .get_mut_or_default(&node)
+            .await
+            .expect("Failed to read the statistics of an Atoma node");
+
+        stats.rejected_interactions += 1;
+    }
+
+    /// Handles an [`Operation::ClaimNodeRewards`] by requesting the chain that created the
+    /// application to pay out the rewards of the `node`.
+    fn claim_node_rewards(&mut self, node: PublicKey, destination: Account, signature: Signature) {
+        let creation_chain_id = self.runtime.application_creator_chain_id();
+
+        self.runtime.send_message(
+            creation_chain_id,
+            Message::PayNodeRewards {
+                node,
+                destination,
+                signature,
+            },
+        );
+    }
+
+    /// Handles a [`Message::PayNodeRewards`] by transferring the unclaimed rewards of the `node`
+    /// from the fee pool to the `destination` account.
+    ///
+    /// The `signature` must be the node's signature over the [`NodeRewardClaim`], so that only
+    /// the node can designate where its rewards are paid out. Suspended nodes forfeit their
+    /// rewards, so their claims are rejected.
+    async fn pay_node_rewards(
+        &mut self,
+        node: PublicKey,
+        destination: Account,
+        signature: Signature,
+    ) {
+        let claim = NodeRewardClaim { node, destination };
+
+        assert!(
+            node.verify(CryptoHash::new(&claim), &signature),
+            "Node reward claim has an invalid signature"
+        );
+        assert!(
+            !self.is_suspended_node(node).await,
+            "Suspended nodes can't claim their rewards"
+        );
+
+        let application_owner = self.application_owner();
+        let Some(stats) = self
+            .state
+            .node_stats
+            .get_mut(&node)
+            .await
+            .expect("Failed to read the statistics of an Atoma node")
+        else {
+            return;
+        };
+
+        let rewards = mem::take(&mut stats.unclaimed_rewards);
+
+        if rewards > Amount::ZERO {
+            stats.claimed_rewards.saturating_add_assign(rewards);
+            self.runtime
+                .transfer(application_owner, destination, rewards);
+        }
+    }
+
+    /// Handles a [`Message::LogVerifiedChatInteraction`] by adding the
+    /// [`LoggedChatInteraction`] to the chat log.
+    ///
+    /// The chat log's Merkle mountain range is updated with the new entry, so that inclusion
+    /// proofs can be produced for it. Afterwards, the chat log's retention policy is applied, if
+    /// there is one.
+    async fn log_verified_chat_interaction(&mut self, logged_interaction: LoggedChatInteraction) {
+        let index = self.state.chat_log_merkle_range.get().len();
+        let new_nodes = self
+            .state
+            .chat_log_merkle_range
+            .get_mut()
+            .push(CryptoHash::new(&logged_interaction));
+
+        for (position, hash) in new_nodes {
+            self.state
+                .chat_log_merkle_nodes
+                .insert(&position, hash)
+                .expect("Failed to store a node of the chat log's Merkle mountain range");
+        }
+
+        self.state
+            .chat_log
+            .insert(&index, logged_interaction.into())
+            .expect("Failed to store the chat log entry");
+
+        if let Some(retention) = *self.state.chat_log_retention.get() {
+            let now = self.runtime.system_time();
+            let older_than = retention.max_age.map(|max_age| now.saturating_sub(max_age));
+
+            self.prune_chat_log_entries(older_than, retention.max_count, retention.keep_digest)
+                .await;
+        }
+    }
+
+    /// Handles an [`Operation::LogEmbedding`] by requesting the [`EmbeddingRecord`] to be
+    /// verified.
+    fn log_embedding(
+        &mut self,
+        embedding: EmbeddingRecord,
+        receipts: Vec<NodeReceipt>,
+        quorum: u32,
+    ) {
+        let creation_chain_id = self.runtime.application_creator_chain_id();
+
+        self.runtime.send_message(
+            creation_chain_id,
+            Message::VerifyEmbedding {
+                embedding,
+                receipts,
+                quorum,
+            },
+        );
+    }
+
+    /// Handles a [`Message::VerifyEmbedding`] by verifying the [`EmbeddingRecord`] and if
+    /// accepted, responding with a [`Message::LogVerifiedEmbedding`].
+    ///
+    /// The embedding must be computed by an allowed model, and attested by the node `receipts`
+    /// like a chat interaction.
+    async fn verify_embedding(
+        &mut self,
+        embedding: EmbeddingRecord,
+        receipts: Vec<NodeReceipt>,
+        quorum: u32,
+    ) {
+        assert_eq!(
+            usize::try_from(embedding.dimensions).ok(),
+            Some(embedding.vector.len()),
+            "Embedding vector does not have the expected number of dimensions"
+        );
+        assert!(
+            self.is_allowed_model(&embedding.model).await,
+            "Embedding was computed by a model that is not allowed"
+        );
+
+        self.assert_node_quorum(&receipts, embedding.id(), 0, quorum)
+            .await;
+
+        let requester_chain_id = self.requester_chain_id();
+
+        self.runtime
+            .send_message(requester_chain_id, Message::LogVerifiedEmbedding(embedding));
+    }
+
+    /// Handles a [`Message::LogVerifiedEmbedding`] by adding the [`EmbeddingRecord`] to the
+    /// embedding log.
+    fn log_verified_embedding(&mut self, embedding: EmbeddingRecord) {
+        self.state.embedding_log.push(embedding);
+    }
+
+    /// Handles an [`Operation::LogImageGeneration`] by requesting the
+    /// [`ImageGenerationRecord`] to be verified.
+    fn log_image_generation(
+        &mut self,
+        generation: ImageGenerationRecord,
+        receipts: Vec<NodeReceipt>,
+        quorum: u32,
+    ) {
+        let creation_chain_id = self.runtime.application_creator_chain_id();
+
+        self.runtime.send_message(
+            creation_chain_id,
+            Message::VerifyImageGeneration {
+                generation,
+                receipts,
+                quorum,
+            },
+        );
+    }
+
+    /// Handles a [`Message::VerifyImageGeneration`] by verifying the [`ImageGenerationRecord`]
+    /// and if accepted, responding with a [`Message::LogVerifiedImageGeneration`].
+    ///
+    /// The images must be generated by an allowed model, and attested by the node `receipts`
+    /// like a chat interaction.
+    async fn verify_image_generation(
+        &mut self,
+        generation: ImageGenerationRecord,
+        receipts: Vec<NodeReceipt>,
+        quorum: u32,
+    ) {
+        assert!(
+            !generation.images.is_empty(),
+            "Image generation record has no images"
+        );
+        assert!(
+            self.is_allowed_model(&generation.model).await,
+            "Images were generated by a model that is not allowed"
+        );
+
+        self.assert_node_quorum(&receipts, generation.id(), 0, quorum)
+            .await;
+
+        let requester_chain_id = self.requester_chain_id();
 
         self.runtime.send_message(
             requester_chain_id,
-            Message::LogVerifiedChatInteraction(interaction),
+            Message::LogVerifiedImageGeneration(generation),
         );
     }
 
-    /// Handles a [`Message::LogVerifiedChatInteraction`] by adding the [`ChatInteraction`] to the
-    /// chat log.
-    fn log_verified_chat_interaction(&mut self, interaction: ChatInteraction) {
-        self.state.chat_log.push(interaction);
+    /// Handles a [`Message::LogVerifiedImageGeneration`] by adding the
+    /// [`ImageGenerationRecord`] to the image log.
+    fn log_verified_image_generation(&mut self, generation: ImageGenerationRecord) {
+        self.state.image_log.push(generation);
+    }
+
+    /// Returns the [`AccountOwner`] of the application's account, which holds the payments and
+    /// the fee pool.
+    fn application_owner(&mut self) -> AccountOwner {
+        AccountOwner::from(self.runtime.application_id().forget_abi())
+    }
+
+    /// Returns the [`BlockTime`] of the block being executed.
+    fn block_time(&mut self) -> BlockTime {
+        BlockTime {
+            timestamp: self.runtime.system_time(),
+            block_height: self.runtime.block_height(),
+        }
+    }
+
+    /// Returns the [`ChainId`] of the chain that sent the message being handled.
+    fn requester_chain_id(&mut self) -> ChainId {
+        self.runtime
+            .message_id()
+            .expect("Requester chain should only be needed when handling a message")
+            .chain_id
     }
 }
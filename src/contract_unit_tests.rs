@@ -2,109 +2,2162 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use std::{
-    collections::{BTreeSet, HashSet},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
     iter, panic,
 };
 
-use atoma_demo::{ChatInteraction, Operation, PublicKey};
+use atoma_demo::{
+    merkle::MerkleMountainRange, ApiToken, AttestationPolicy, AttestationReport,
+    AttestationStatement, AuditLogEntry, BlockTime, ChatFee, ChatInteraction, ChatLogEntry,
+    ChatLogRetention, EmbeddingRecord, EnclaveMeasurement, ImageGenerationRecord,
+    LoggedChatInteraction, NodeAttestation, NodeMisbehaviorEvidence, NodeReceipt, NodeResponse,
+    NodeRewardClaim, NodeStats, NodeSuspension, Operation, PrunedChatLogRange, PublicKey,
+    RedactedChatInteraction, Signature, SignatureScheme, SignedNodeResponse, SuspensionReason,
+};
+use ed25519_dalek::{Signer, SigningKey};
 use linera_sdk::{
-    linera_base_types::{ApplicationId, ChainId, Destination},
+    linera_base_types::{
+        Account, AccountOwner, Amount, ApplicationId, BlockHeight, ChainId, ChainOwnership,
+        CryptoHash, Destination, MessageId, TimeDelta, Timestamp,
+    },
     util::BlockingWait,
     Contract, ContractRuntime, Resources, SendMessageRequest,
 };
 use proptest::{
-    prelude::{Arbitrary, BoxedStrategy},
+    prelude::{any, prop_assume, Arbitrary, BoxedStrategy},
     sample::size_range,
     strategy::Strategy,
 };
 use rand::Rng;
 use test_strategy::proptest;
 
-use super::{ApplicationContract, Message};
+use super::{ApplicationContract, Message};
+
+/// Tests if nodes can be added to and removed from the set of active Atoma nodes.
+#[proptest]
+fn updating_nodes(
+    application_id: ApplicationId<atoma_demo::ApplicationAbi>,
+    creator_chain_id: ChainId,
+    test_operations: TestUpdateNodesOperations,
+) {
+    let mut test = NodeSetTest::new(application_id, creator_chain_id);
+
+    for test_operation in test_operations.0 {
+        let operation = test.prepare_operation(test_operation);
+
+        test.contract.execute_operation(operation).blocking_wait();
+
+        test.check_active_atoma_nodes();
+    }
+}
+
+/// Tests if the set of active Atoma nodes can only be changed in the chain where the application
+/// was created.
+#[proptest]
+fn only_creation_chain_can_track_nodes(
+    application_id: ApplicationId<atoma_demo::ApplicationAbi>,
+    creator_chain_id: ChainId,
+    chain_id: ChainId,
+    test_operation: TestUpdateNodesOperation,
+) {
+    let result = panic::catch_unwind(move || {
+        let mut test = NodeSetTest::new(application_id, creator_chain_id).with_chain_id(chain_id);
+        let operation = test.prepare_operation(test_operation);
+
+        test.contract.execute_operation(operation).blocking_wait();
+
+        test
+    });
+
+    match result {
+        Ok(test) => {
+            assert_eq!(
+                chain_id, creator_chain_id,
+                "Contract executed `Operation::UpdateNodes` \
+                outside of the application's creation chain"
+            );
+            test.check_active_atoma_nodes();
+        }
+        Err(_panic_cause) => {
+            assert_ne!(
+                chain_id, creator_chain_id,
+                "Contract failed to execute `Operation::UpdateNodes` \
+                on the application's creation chain"
+            );
+        }
+    }
+}
+
+/// Tests if the contract rejects adding a node twice.
+#[proptest]
+fn cant_add_and_remove_node_in_the_same_operation(
+    application_id: ApplicationId<atoma_demo::ApplicationAbi>,
+    creator_chain_id: ChainId,
+    #[any(size_range(1..5).lift())] conflicting_nodes: HashSet<PublicKey>,
+    mut test_operation: TestUpdateNodesOperation,
+) {
+    let result = panic::catch_unwind(move || {
+        let mut test = NodeSetTest::new(application_id, creator_chain_id);
+
+        test_operation.add.extend(conflicting_nodes.iter().copied());
+        test_operation.remove.extend(conflicting_nodes);
+
+        let operation = test.prepare_operation(test_operation);
+
+        test.contract.execute_operation(operation).blocking_wait();
+    });
+
+    assert!(result.is_err());
+}
+
+/// Tests if models can be added to and removed from the set of allowed models, and only in the
+/// chain where the application was created.
+#[proptest]
+fn updating_models(
+    application_id: ApplicationId<atoma_demo::ApplicationAbi>,
+    creator_chain_id: ChainId,
+    chain_id: ChainId,
+    #[strategy(proptest::collection::btree_set("[A-Za-z0-9./-]+", 0..10))] models: BTreeSet<String>,
+    #[strategy(0..=#models.len())] removed_count: usize,
+) {
+    let models_to_remove = models
+        .iter()
+        .take(removed_count)
+        .cloned()
+        .collect::<Vec<_>>();
+    let expected_models = models
+        .iter()
+        .skip(removed_count)
+        .cloned()
+        .collect::<BTreeSet<_>>();
+
+    let result = panic::catch_unwind(move || {
+        let mut contract = setup_contract();
+
+        contract
+            .runtime
+            .set_application_id(application_id)
+            .set_chain_id(chain_id)
+            .set_application_creator_chain_id(creator_chain_id);
+
+        contract
+            .execute_operation(Operation::UpdateModels {
+                add: models.into_iter().collect(),
+                remove: vec![],
+            })
+            .blocking_wait();
+
+        contract
+            .execute_operation(Operation::UpdateModels {
+                add: vec![],
+                remove: models_to_remove,
+            })
+            .blocking_wait();
+
+        contract
+    });
+
+    match result {
+        Ok(contract) => {
+            assert_eq!(chain_id, creator_chain_id);
+
+            let mut allowed_models = BTreeSet::new();
+            contract
+                .state
+                .allowed_models
+                .for_each_index(|model| {
+                    allowed_models.insert(model);
+                    Ok(())
+                })
+                .blocking_wait()
+                .expect("Failed to read allowed models from state");
+
+            assert_eq!(allowed_models, expected_models);
+        }
+        Err(_panic_cause) => assert_ne!(chain_id, creator_chain_id),
+    }
+}
+
+/// Tests if API tokens can only be stored in and removed from the chain's state by the chain's
+/// owners.
+#[proptest]
+fn updating_api_tokens(
+    #[strategy(proptest::collection::btree_map("[a-z0-9-]+", any::<([u8; 32], Vec<u8>)>(), 0..10))]
+    tokens: BTreeMap<String, ([u8; 32], Vec<u8>)>,
+    #[strategy(0..=#tokens.len())] removed_count: usize,
+    owner: AccountOwner,
+    signer: Option<AccountOwner>,
+) {
+    let api_tokens = tokens
+        .into_iter()
+        .map(|(name, (key, ciphertext))| ApiToken {
+            name,
+            key,
+            ciphertext,
+        })
+        .collect::<Vec<_>>();
+    let tokens_to_remove = api_tokens
+        .iter()
+        .take(removed_count)
+        .map(|api_token| api_token.name.clone())
+        .collect::<Vec<_>>();
+    let expected_tokens = api_tokens
+        .iter()
+        .skip(removed_count)
+        .map(|api_token| (api_token.name.clone(), api_token.clone()))
+        .collect::<BTreeMap<_, _>>();
+
+    let result = panic::catch_unwind(move || {
+        let mut contract = setup_contract();
+
+        contract
+            .runtime
+            .set_chain_ownership(ChainOwnership::single(owner))
+            .set_authenticated_signer(signer);
+
+        contract
+            .execute_operation(Operation::UpdateApiTokens {
+                add: api_tokens,
+                remove: vec![],
+            })
+            .blocking_wait();
+
+        contract
+            .execute_operation(Operation::UpdateApiTokens {
+                add: vec![],
+                remove: tokens_to_remove,
+            })
+            .blocking_wait();
+
+        contract
+    });
+
+    match result {
+        Ok(contract) => {
+            assert_eq!(signer, Some(owner));
+
+            let mut stored_tokens = BTreeMap::new();
+            contract
+                .state
+                .api_tokens
+                .for_each_index_value(|name, api_token| {
+                    stored_tokens.insert(name, api_token.into_owned());
+                    Ok(())
+                })
+                .blocking_wait()
+                .expect("Failed to read API tokens from state");
+
+            assert_eq!(stored_tokens, expected_tokens);
+        }
+        Err(_panic_cause) => assert_ne!(signer, Some(owner)),
+    }
+}
+
+/// Tests if chat interactions are only approved if they were produced by an allowed model.
+#[proptest]
+fn chat_interaction_models_are_verified(
+    requester_message_id: MessageId,
+    requester: Option<AccountOwner>,
+    interaction: ChatInteraction,
+    requested_at: BlockTime,
+    verified_at: BlockTime,
+    is_allowed: bool,
+) {
+    let result = panic::catch_unwind(move || {
+        let mut contract = setup_contract();
+
+        contract
+            .runtime
+            .set_message_id(requester_message_id)
+            .set_authenticated_signer(requester)
+            .set_system_time(verified_at.timestamp)
+            .set_block_height(verified_at.block_height);
+
+        if is_allowed {
+            contract
+                .state
+                .allowed_models
+                .insert(&interaction.model)
+                .expect("Failed to add a model to the set of allowed models");
+        }
+
+        contract
+            .execute_message(Message::VerifySignature {
+                interaction: interaction.clone(),
+                requested_at,
+                payment: Amount::ZERO,
+                receipts: vec![],
+                quorum: 0,
+            })
+            .blocking_wait();
+
+        (contract, interaction)
+    });
+
+    match result {
+        Ok((contract, interaction)) => {
+            assert!(is_allowed);
+
+            let messages = contract.runtime.created_send_message_requests();
+
+            assert_eq!(messages.len(), 1);
+            assert_eq!(
+                messages[0].destination,
+                Destination::Recipient(requester_message_id.chain_id)
+            );
+            assert_eq!(
+                messages[0].message,
+                Message::LogVerifiedChatInteraction(LoggedChatInteraction {
+                    interaction,
+                    requester,
+                    requester_chain_id: requester_message_id.chain_id,
+                    requested_at,
+                    verified_at,
+                })
+            );
+        }
+        Err(_panic_cause) => assert!(!is_allowed),
+    }
+}
+
+/// Tests if the audit log can only be configured in the chain where the application was created.
+#[proptest]
+fn only_creation_chain_can_configure_audit_log(
+    creator_chain_id: ChainId,
+    chain_id: ChainId,
+    enabled: bool,
+) {
+    let result = panic::catch_unwind(move || {
+        let mut contract = setup_contract();
+
+        contract
+            .runtime
+            .set_application_creator_chain_id(creator_chain_id)
+            .set_chain_id(chain_id);
+
+        contract
+            .execute_operation(Operation::ConfigureAuditLog { enabled })
+            .blocking_wait();
+
+        contract
+    });
+
+    match result {
+        Ok(contract) => {
+            assert_eq!(chain_id, creator_chain_id);
+            assert_eq!(*contract.state.is_audit_log_enabled.get(), enabled);
+        }
+        Err(_panic_cause) => assert_ne!(chain_id, creator_chain_id),
+    }
+}
+
+/// Tests if verified chat interactions are recorded in the audit log only if it is enabled.
+#[proptest]
+fn verified_chat_interactions_are_audited(
+    creator_chain_id: ChainId,
+    requester_message_id: MessageId,
+    requester: Option<AccountOwner>,
+    interactions: Vec<(ChatInteraction, BlockTime)>,
+    verified_at: BlockTime,
+    is_audit_log_enabled: bool,
+) {
+    let mut contract = setup_contract();
+
+    contract
+        .runtime
+        .set_application_creator_chain_id(creator_chain_id)
+        .set_chain_id(creator_chain_id)
+        .set_message_id(requester_message_id)
+        .set_authenticated_signer(requester)
+        .set_system_time(verified_at.timestamp)
+        .set_block_height(verified_at.block_height);
+
+    contract
+        .execute_operation(Operation::ConfigureAuditLog {
+            enabled: is_audit_log_enabled,
+        })
+        .blocking_wait();
+
+    for (interaction, requested_at) in interactions.clone() {
+        contract
+            .state
+            .allowed_models
+            .insert(&interaction.model)
+            .expect("Failed to add a model to the set of allowed models");
+
+        contract
+            .execute_message(Message::VerifySignature {
+                interaction,
+                requested_at,
+                payment: Amount::ZERO,
+                receipts: vec![],
+                quorum: 0,
+            })
+            .blocking_wait();
+    }
+
+    let audit_log = contract
+        .state
+        .audit_log
+        .read(..)
+        .blocking_wait()
+        .expect("Failed to read the audit log from the state");
+
+    let expected_audit_log = if is_audit_log_enabled {
+        interactions
+            .into_iter()
+            .map(|(interaction, requested_at)| AuditLogEntry {
+                interaction_id: interaction.id(),
+                requester,
+                requester_chain_id: requester_message_id.chain_id,
+                requested_at,
+                verified_at,
+            })
+            .collect()
+    } else {
+        vec![]
+    };
+
+    assert_eq!(audit_log, expected_audit_log);
+}
+
+/// Tests if chat interactions are requested to be verified.
+#[proptest]
+fn chat_interaction_is_requested_to_be_verified(
+    application_id: ApplicationId<atoma_demo::ApplicationAbi>,
+    creator_chain_id: ChainId,
+    interaction: ChatInteraction,
+    requested_at: BlockTime,
+) {
+    let mut contract = setup_contract();
+
+    contract
+        .runtime
+        .set_application_id(application_id)
+        .set_application_creator_chain_id(creator_chain_id)
+        .set_system_time(requested_at.timestamp)
+        .set_block_height(requested_at.block_height);
+
+    contract
+        .execute_operation(Operation::LogChatInteraction {
+            interaction: interaction.clone(),
+            payment: Amount::ZERO,
+            receipts: vec![],
+            quorum: 0,
+        })
+        .blocking_wait();
+
+    let messages = contract.runtime.created_send_message_requests();
+
+    assert_eq!(messages.len(), 1);
+    assert_eq!(
+        messages[0],
+        SendMessageRequest {
+            destination: Destination::Recipient(creator_chain_id),
+            authenticated: true,
+            is_tracked: false,
+            grant: Resources::default(),
+            message: Message::VerifySignature {
+                interaction,
+                requested_at,
+                payment: Amount::ZERO,
+                receipts: vec![],
+                quorum: 0,
+            },
+        }
+    );
+}
+
+/// Tests if the payment for verifying a chat interaction is transferred to the application's
+/// account on the creation chain.
+#[proptest]
+fn chat_interaction_payment_is_transferred(
+    application_id: ApplicationId<atoma_demo::ApplicationAbi>,
+    creator_chain_id: ChainId,
+    chain_id: ChainId,
+    interaction: ChatInteraction,
+    requested_at: BlockTime,
+    #[strategy(1..=u128::from(u64::MAX))] payment_attos: u128,
+) {
+    prop_assume!(chain_id != creator_chain_id);
+
+    let payment = Amount::from_attos(payment_attos);
+    let mut contract = setup_contract();
+
+    contract
+        .runtime
+        .set_application_id(application_id)
+        .set_application_creator_chain_id(creator_chain_id)
+        .set_chain_id(chain_id)
+        .set_chain_balance(payment)
+        .set_system_time(requested_at.timestamp)
+        .set_block_height(requested_at.block_height);
+
+    contract
+        .execute_operation(Operation::LogChatInteraction {
+            interaction: interaction.clone(),
+            payment,
+            receipts: vec![],
+            quorum: 0,
+        })
+        .blocking_wait();
+
+    let application_account = Account::new(
+        creator_chain_id,
+        AccountOwner::from(application_id.forget_abi()),
+    );
+
+    assert_eq!(contract.runtime.chain_balance(), Amount::ZERO);
+    assert_eq!(
+        contract.runtime.outgoing_transfers(),
+        &HashMap::from([(application_account, payment)])
+    );
+
+    let messages = contract.runtime.created_send_message_requests();
+
+    assert_eq!(messages.len(), 1);
+    assert_eq!(
+        messages[0].message,
+        Message::VerifySignature {
+            interaction,
+            requested_at,
+            payment,
+            receipts: vec![],
+            quorum: 0,
+        }
+    );
+}
+
+/// Tests if the chat fee can only be configured in the chain where the application was created.
+#[proptest]
+fn only_creation_chain_can_configure_chat_fee(
+    creator_chain_id: ChainId,
+    chain_id: ChainId,
+    treasury_chain_id: ChainId,
+    treasury_owner: AccountOwner,
+    #[strategy(0..=u128::from(u64::MAX))] fee_attos: u128,
+) {
+    let fee = ChatFee {
+        amount: Amount::from_attos(fee_attos),
+        treasury: Account::new(treasury_chain_id, treasury_owner),
+        node_reward: Amount::ZERO,
+    };
+
+    let result = panic::catch_unwind(move || {
+        let mut contract = setup_contract();
+
+        contract
+            .runtime
+            .set_application_creator_chain_id(creator_chain_id)
+            .set_chain_id(chain_id);
+
+        contract
+            .execute_operation(Operation::ConfigureChatFee { fee: Some(fee) })
+            .blocking_wait();
+
+        contract
+    });
+
+    match result {
+        Ok(contract) => {
+            assert_eq!(chain_id, creator_chain_id);
+            assert_eq!(*contract.state.chat_fee.get(), Some(fee));
+        }
+        Err(_panic_cause) => assert_ne!(chain_id, creator_chain_id),
+    }
+}
+
+/// Tests if the chat fee is charged from the payment when verifying chat interactions, and if
+/// the rest of the payment is refunded to the requester chain.
+#[proptest]
+fn chat_fees_are_charged(
+    application_id: ApplicationId<atoma_demo::ApplicationAbi>,
+    creator_chain_id: ChainId,
+    requester_message_id: MessageId,
+    requester: Option<AccountOwner>,
+    treasury_chain_id: ChainId,
+    treasury_owner: AccountOwner,
+    interaction: ChatInteraction,
+    requested_at: BlockTime,
+    verified_at: BlockTime,
+    #[strategy(0..=u128::from(u64::MAX))] fee_attos: u128,
+    #[strategy(0..=u128::from(u64::MAX))] payment_attos: u128,
+) {
+    let requester_chain_id = requester_message_id.chain_id;
+    let treasury = Account::new(treasury_chain_id, treasury_owner);
+
+    prop_assume!(requester_chain_id != creator_chain_id);
+    prop_assume!(treasury_chain_id != creator_chain_id);
+    prop_assume!(treasury != Account::chain(requester_chain_id));
+
+    let fee = ChatFee {
+        amount: Amount::from_attos(fee_attos),
+        treasury,
+        node_reward: Amount::ZERO,
+    };
+    let payment = Amount::from_attos(payment_attos);
+    let application_owner = AccountOwner::from(application_id.forget_abi());
+
+    let result = panic::catch_unwind(move || {
+        let mut contract = setup_contract();
+
+        contract
+            .runtime
+            .set_application_id(application_id)
+            .set_application_creator_chain_id(creator_chain_id)
+            .set_chain_id(creator_chain_id)
+            .set_message_id(requester_message_id)
+            .set_authenticated_signer(requester)
+            .set_system_time(verified_at.timestamp)
+            .set_block_height(verified_at.block_height)
+            .set_owner_balance(application_owner, payment);
+
+        contract
+            .state
+            .allowed_models
+            .insert(&interaction.model)
+            .expect("Failed to add a model to the set of allowed models");
+
+        contract
+            .execute_operation(Operation::ConfigureChatFee { fee: Some(fee) })
+            .blocking_wait();
+
+        contract
+            .execute_message(Message::VerifySignature {
+                interaction,
+                requested_at,
+                payment,
+                receipts: vec![],
+                quorum: 0,
+            })
+            .blocking_wait();
+
+        contract
+    });
+
+    match result {
+        Ok(mut contract) => {
+            let refund = payment
+                .try_sub(fee.amount)
+                .expect("Chat interaction should only be verified if the fee is paid");
+            let expected_transfers = [
+                (treasury, fee.amount),
+                (Account::chain(requester_chain_id), refund),
+            ]
+            .into_iter()
+            .filter(|(_, amount)| *amount > Amount::ZERO)
+            .collect::<HashMap<_, _>>();
+
+            assert_eq!(contract.runtime.outgoing_transfers(), &expected_transfers);
+            assert_eq!(
+                contract.runtime.owner_balance(application_owner),
+                Amount::ZERO
+            );
+        }
+        Err(_panic_cause) => assert!(payment < fee.amount),
+    }
+}
+
+/// Tests if the Atoma nodes that produce verified chat interactions are credited with them and
+/// rewarded from the chat fee.
+#[proptest]
+fn verified_chat_interactions_reward_nodes(
+    application_id: ApplicationId<atoma_demo::ApplicationAbi>,
+    creator_chain_id: ChainId,
+    requester_message_id: MessageId,
+    treasury_chain_id: ChainId,
+    treasury_owner: AccountOwner,
+    node_secret: [u8; 32],
+    #[any(size_range(0..5).lift())] interactions: Vec<(ChatInteraction, CryptoHash, u64)>,
+    #[strategy(0..=u128::from(u64::MAX))] fee_attos: u128,
+    #[strategy(0..=#fee_attos)] node_reward_attos: u128,
+) {
+    prop_assume!(treasury_chain_id != creator_chain_id);
+
+    let node = TestNode::new(node_secret);
+    let fee = ChatFee {
+        amount: Amount::from_attos(fee_attos),
+        treasury: Account::new(treasury_chain_id, treasury_owner),
+        node_reward: Amount::from_attos(node_reward_attos),
+    };
+    let application_owner = AccountOwner::from(application_id.forget_abi());
+    let interaction_count = interactions.len() as u128;
+    let mut contract = setup_contract();
+
+    contract
+        .runtime
+        .set_application_id(application_id)
+        .set_application_creator_chain_id(creator_chain_id)
+        .set_chain_id(creator_chain_id)
+        .set_message_id(requester_message_id)
+        .set_authenticated_signer(None)
+        .set_system_time(Timestamp::from(0))
+        .set_block_height(BlockHeight(0))
+        .set_owner_balance(
+            application_owner,
+            fee.amount.saturating_mul(interaction_count),
+        );
+
+    contract
+        .execute_operation(Operation::UpdateNodes {
+            add: vec![node.public_key()],
+            remove: vec![],
+        })
+        .blocking_wait();
+    contract
+        .execute_operation(Operation::ConfigureChatFee { fee: Some(fee) })
+        .blocking_wait();
+
+    for (interaction, request_id, tokens) in &interactions {
+        contract
+            .state
+            .allowed_models
+            .insert(&interaction.model)
+            .expect("Failed to add a model to the set of allowed models");
+
+        contract
+            .execute_message(Message::VerifySignature {
+                interaction: interaction.clone(),
+                requested_at: BlockTime {
+                    timestamp: Timestamp::from(0),
+                    block_height: BlockHeight(0),
+                },
+                payment: fee.amount,
+                receipts: vec![node.receipt(*request_id, interaction, *tokens)],
+                quorum: 1,
+            })
+            .blocking_wait();
+    }
+
+    let expected_rewards = fee.node_reward.saturating_mul(interaction_count);
+    let expected_treasury_fees = fee
+        .amount
+        .try_sub(fee.node_reward)
+        .expect("Node reward should not exceed the chat fee")
+        .saturating_mul(interaction_count);
+    let expected_stats = NodeStats {
+        verified_interactions: interactions.len() as u64,
+        rejected_interactions: 0,
+        reports: 0,
+        active_since: Some(Timestamp::from(0)),
+        tokens_served: interactions
+            .iter()
+            .fold(0, |total, (_, _, tokens)| total.saturating_add(*tokens)),
+        unclaimed_rewards: expected_rewards,
+        claimed_rewards: Amount::ZERO,
+    };
+    let expected_transfers = iter::once((fee.treasury, expected_treasury_fees))
+        .filter(|(_, amount)| *amount > Amount::ZERO)
+        .collect::<HashMap<_, _>>();
+
+    let stats = contract
+        .state
+        .node_stats
+        .get(&node.public_key())
+        .blocking_wait()
+        .expect("Failed to read node statistics from the state");
+
+    assert_eq!(stats, Some(expected_stats));
+
+    assert_eq!(contract.runtime.outgoing_transfers(), &expected_transfers);
+    assert_eq!(
+        contract.runtime.owner_balance(application_owner),
+        expected_rewards
+    );
+}
+
+/// Tests if chat interactions are only verified if their node receipts are signed by an active
+/// Atoma node that isn't suspended.
+#[proptest]
+fn node_receipts_are_verified(
+    requester_message_id: MessageId,
+    scheme: SignatureScheme,
+    node_secret: [u8; 32],
+    interaction: ChatInteraction,
+    request_id: CryptoHash,
+    tokens: u64,
+    requested_at: BlockTime,
+    verified_at: BlockTime,
+    suspension: Option<NodeSuspension>,
+    is_active_node: bool,
+    is_signature_valid: bool,
+) {
+    let is_suspended = suspension.is_some();
+
+    let result = panic::catch_unwind(move || {
+        let node = TestNode::with_scheme(scheme, node_secret);
+        let mut receipt = node.receipt(request_id, &interaction, tokens);
+        let mut contract = setup_contract();
+
+        if !is_signature_valid {
+            receipt.tokens = tokens.wrapping_add(1);
+        }
+
+        contract
+            .runtime
+            .set_message_id(requester_message_id)
+            .set_authenticated_signer(None)
+            .set_system_time(verified_at.timestamp)
+            .set_block_height(verified_at.block_height);
+
+        contract
+            .state
+            .allowed_models
+            .insert(&interaction.model)
+            .expect("Failed to add a model to the set of allowed models");
+
+        if is_active_node {
+            contract
+                .state
+                .active_atoma_nodes
+                .insert(&node.public_key())
+                .expect("Failed to add a node to the set of active Atoma nodes");
+        }
+
+        if let Some(suspension) = suspension {
+            contract
+                .state
+                .suspended_atoma_nodes
+                .insert(&node.public_key(), suspension)
+                .expect("Failed to suspend an Atoma node");
+        }
+
+        contract
+            .execute_message(Message::VerifySignature {
+                interaction,
+                requested_at,
+                payment: Amount::ZERO,
+                receipts: vec![receipt],
+                quorum: 1,
+            })
+            .blocking_wait();
+    });
+
+    assert_eq!(
+        result.is_ok(),
+        is_active_node && is_signature_valid && !is_suspended
+    );
+}
+
+/// Tests if chat interactions are only verified if they are attested by a quorum of different
+/// active Atoma nodes.
+#[proptest]
+fn node_receipt_quorum_is_required(
+    requester_message_id: MessageId,
+    #[any(size_range(0..5).lift())] node_secrets: BTreeSet<[u8; 32]>,
+    interaction: ChatInteraction,
+    request_id: CryptoHash,
+    tokens: u64,
+    requested_at: BlockTime,
+    verified_at: BlockTime,
+    #[strategy(0..=6_u32)] quorum: u32,
+    has_duplicate_receipt: bool,
+) {
+    let node_count = node_secrets.len();
+    let has_duplicate_receipt = has_duplicate_receipt && node_count > 0;
+
+    let result = panic::catch_unwind(move || {
+        let nodes = node_secrets
+            .into_iter()
+            .map(TestNode::new)
+            .collect::<Vec<_>>();
+        let mut receipts = nodes
+            .iter()
+            .map(|node| node.receipt(request_id, &interaction, tokens))
+            .collect::<Vec<_>>();
+        let mut contract = setup_contract();
+
+        if has_duplicate_receipt {
+            receipts.push(receipts[0].clone());
+        }
+
+        contract
+            .runtime
+            .set_message_id(requester_message_id)
+            .set_authenticated_signer(None)
+            .set_system_time(verified_at.timestamp)
+            .set_block_height(verified_at.block_height);
+
+        contract
+            .state
+            .allowed_models
+            .insert(&interaction.model)
+            .expect("Failed to add a model to the set of allowed models");
+
+        for node in &nodes {
+            contract
+                .state
+                .active_atoma_nodes
+                .insert(&node.public_key())
+                .expect("Failed to add a node to the set of active Atoma nodes");
+        }
+
+        contract
+            .execute_message(Message::VerifySignature {
+                interaction,
+                requested_at,
+                payment: Amount::ZERO,
+                receipts,
+                quorum,
+            })
+            .blocking_wait();
+    });
+
+    assert_eq!(
+        result.is_ok(),
+        !has_duplicate_receipt && node_count >= quorum as usize
+    );
+}
+
+/// Tests if the node reward of a chat interaction attested by multiple Atoma nodes is split
+/// evenly between them, with the remainder sent to the treasury.
+#[proptest]
+fn node_rewards_are_split_between_attesting_nodes(
+    application_id: ApplicationId<atoma_demo::ApplicationAbi>,
+    creator_chain_id: ChainId,
+    requester_message_id: MessageId,
+    treasury_chain_id: ChainId,
+    treasury_owner: AccountOwner,
+    #[any(size_range(1..5).lift())] node_secrets: BTreeSet<[u8; 32]>,
+    interaction: ChatInteraction,
+    request_id: CryptoHash,
+    tokens: u64,
+    #[strategy(0..=u128::from(u64::MAX))] fee_attos: u128,
+    #[strategy(0..=#fee_attos)] node_reward_attos: u128,
+) {
+    prop_assume!(treasury_chain_id != creator_chain_id);
+
+    let nodes = node_secrets
+        .into_iter()
+        .map(TestNode::new)
+        .collect::<Vec<_>>();
+    let fee = ChatFee {
+        amount: Amount::from_attos(fee_attos),
+        treasury: Account::new(treasury_chain_id, treasury_owner),
+        node_reward: Amount::from_attos(node_reward_attos),
+    };
+    let application_owner = AccountOwner::from(application_id.forget_abi());
+    let node_count = nodes.len() as u128;
+    let mut contract = setup_contract();
+
+    contract
+        .runtime
+        .set_application_id(application_id)
+        .set_application_creator_chain_id(creator_chain_id)
+        .set_chain_id(creator_chain_id)
+        .set_message_id(requester_message_id)
+        .set_authenticated_signer(None)
+        .set_system_time(Timestamp::from(0))
+        .set_block_height(BlockHeight(0))
+        .set_owner_balance(application_owner, fee.amount);
+
+    contract
+        .execute_operation(Operation::UpdateNodes {
+            add: nodes.iter().map(TestNode::public_key).collect(),
+            remove: vec![],
+        })
+        .blocking_wait();
+    contract
+        .execute_operation(Operation::ConfigureChatFee { fee: Some(fee) })
+        .blocking_wait();
+    contract
+        .state
+        .allowed_models
+        .insert(&interaction.model)
+        .expect("Failed to add a model to the set of allowed models");
+
+    contract
+        .execute_message(Message::VerifySignature {
+            interaction: interaction.clone(),
+            requested_at: BlockTime {
+                timestamp: Timestamp::from(0),
+                block_height: BlockHeight(0),
+            },
+            payment: fee.amount,
+            receipts: nodes
+                .iter()
+                .map(|node| node.receipt(request_id, &interaction, tokens))
+                .collect(),
+            quorum: nodes.len() as u32,
+        })
+        .blocking_wait();
+
+    let expected_reward = Amount::from_attos(node_reward_attos / node_count);
+    let expected_rewards = expected_reward.saturating_mul(node_count);
+    let expected_treasury_fees = fee
+        .amount
+        .try_sub(expected_rewards)
+        .expect("Node rewards should not exceed the chat fee");
+    let expected_transfers = iter::once((fee.treasury, expected_treasury_fees))
+        .filter(|(_, amount)| *amount > Amount::ZERO)
+        .collect::<HashMap<_, _>>();
+
+    for node in &nodes {
+        let stats = contract
+            .state
+            .node_stats
+            .get(&node.public_key())
+            .blocking_wait()
+            .expect("Failed to read node statistics from the state")
+            .expect("Node should have statistics");
+
+        assert_eq!(stats.verified_interactions, 1);
+        assert_eq!(stats.tokens_served, tokens);
+        assert_eq!(stats.unclaimed_rewards, expected_reward);
+    }
+
+    assert_eq!(contract.runtime.outgoing_transfers(), &expected_transfers);
+    assert_eq!(
+        contract.runtime.owner_balance(application_owner),
+        expected_rewards
+    );
+}
+
+/// Tests if chat interactions produced by a model that isn't allowed are counted as rejections
+/// of the node that signed their receipt, and if the payment is refunded.
+#[proptest]
+fn disallowed_models_count_as_node_rejections(
+    application_id: ApplicationId<atoma_demo::ApplicationAbi>,
+    creator_chain_id: ChainId,
+    requester_message_id: MessageId,
+    node_secret: [u8; 32],
+    interaction: ChatInteraction,
+    request_id: CryptoHash,
+    tokens: u64,
+    requested_at: BlockTime,
+    verified_at: BlockTime,
+    #[strategy(0..=u128::from(u64::MAX))] payment_attos: u128,
+) {
+    prop_assume!(requester_message_id.chain_id != creator_chain_id);
+
+    let node = TestNode::new(node_secret);
+    let payment = Amount::from_attos(payment_attos);
+    let application_owner = AccountOwner::from(application_id.forget_abi());
+    let mut contract = setup_contract();
+
+    contract
+        .runtime
+        .set_application_id(application_id)
+        .set_application_creator_chain_id(creator_chain_id)
+        .set_chain_id(creator_chain_id)
+        .set_message_id(requester_message_id)
+        .set_authenticated_signer(None)
+        .set_system_time(verified_at.timestamp)
+        .set_block_height(verified_at.block_height)
+        .set_owner_balance(application_owner, payment);
+
+    contract
+        .execute_operation(Operation::UpdateNodes {
+            add: vec![node.public_key()],
+            remove: vec![],
+        })
+        .blocking_wait();
+
+    contract
+        .execute_message(Message::VerifySignature {
+            interaction: interaction.clone(),
+            requested_at,
+            payment,
+            receipts: vec![node.receipt(request_id, &interaction, tokens)],
+            quorum: 1,
+        })
+        .blocking_wait();
+
+    let stats = contract
+        .state
+        .node_stats
+        .get(&node.public_key())
+        .blocking_wait()
+        .expect("Failed to read node statistics from the state")
+        .expect("Node should have statistics");
+    let expected_transfers = iter::once((Account::chain(requester_message_id.chain_id), payment))
+        .filter(|(_, amount)| *amount > Amount::ZERO)
+        .collect::<HashMap<_, _>>();
+
+    assert_eq!(stats.rejected_interactions, 1);
+    assert_eq!(stats.verified_interactions, 0);
+    assert!(contract.runtime.created_send_message_requests().is_empty());
+    assert_eq!(contract.runtime.outgoing_transfers(), &expected_transfers);
+    assert_eq!(
+        contract.runtime.owner_balance(application_owner),
+        Amount::ZERO
+    );
+}
+
+/// Tests if node reports are sent to the chain where the application was created.
+#[proptest]
+fn node_reports_are_sent_to_creation_chain(
+    creator_chain_id: ChainId,
+    node: PublicKey,
+    evidence: NodeMisbehaviorEvidence,
+) {
+    let mut contract = setup_contract();
+
+    contract
+        .runtime
+        .set_application_creator_chain_id(creator_chain_id);
+
+    contract
+        .execute_operation(Operation::ReportNode {
+            node,
+            evidence: evidence.clone(),
+        })
+        .blocking_wait();
+
+    let messages = contract.runtime.created_send_message_requests();
+
+    assert_eq!(messages.len(), 1);
+    assert_eq!(
+        messages[0],
+        SendMessageRequest {
+            destination: Destination::Recipient(creator_chain_id),
+            authenticated: false,
+            is_tracked: false,
+            grant: Resources::default(),
+            message: Message::SuspendNode { node, evidence },
+        }
+    );
+}
+
+/// Tests if nodes are suspended when reported with two different responses signed for the same
+/// choice of the same request, keeping any previous suspension.
+#[proptest]
+fn conflicting_responses_suspend_nodes(
+    scheme: SignatureScheme,
+    node_secret: [u8; 32],
+    other_secret: [u8; 32],
+    first: NodeResponse,
+    mut second: NodeResponse,
+    previous_suspension: Option<NodeSuspension>,
+    suspended_at: BlockTime,
+    is_same_request: bool,
+    is_same_choice: bool,
+    is_signed_by_node: bool,
+) {
+    prop_assume!(node_secret != other_secret);
+
+    if is_same_request {
+        second.request_id = first.request_id;
+    }
+
+    if is_same_choice {
+        second.choice_index = first.choice_index;
+    }
+
+    let is_evidence_valid = is_same_request
+        && second.choice_index == first.choice_index
+        && is_signed_by_node
+        && first != second;
+    let node = TestNode::with_scheme(scheme, node_secret).public_key();
+
+    let result = panic::catch_unwind(move || {
+        let node = TestNode::with_scheme(scheme, node_secret);
+        let second_signer = if is_signed_by_node {
+            TestNode::with_scheme(scheme, node_secret)
+        } else {
+            TestNode::with_scheme(scheme, other_secret)
+        };
+        let mut contract = setup_contract();
+
+        contract
+            .runtime
+            .set_system_time(suspended_at.timestamp)
+            .set_block_height(suspended_at.block_height);
+
+        if let Some(suspension) = previous_suspension {
+            contract
+                .state
+                .suspended_atoma_nodes
+                .insert(&node.public_key(), suspension)
+                .expect("Failed to suspend an Atoma node");
+        }
+
+        contract
+            .execute_message(Message::SuspendNode {
+                node: node.public_key(),
+                evidence: NodeMisbehaviorEvidence::ConflictingResponses {
+                    first: node.sign_response(first),
+                    second: second_signer.sign_response(second),
+                },
+            })
+            .blocking_wait();
+
+        contract
+    });
+
+    match result {
+        Ok(contract) => {
+            assert!(is_evidence_valid);
+
+            let suspension = contract
+                .state
+                .suspended_atoma_nodes
+                .get(&node)
+                .blocking_wait()
+                .expect("Failed to read suspended nodes from the state");
+            let expected_suspension = previous_suspension.unwrap_or(NodeSuspension {
+                reason: SuspensionReason::ConflictingResponses,
+                suspended_at,
+            });
+
+            let stats = contract
+                .state
+                .node_stats
+                .get(&node)
+                .blocking_wait()
+                .expect("Failed to read node statistics from the state")
+                .expect("Reported node should have statistics");
+
+            assert_eq!(suspension, Some(expected_suspension));
+            assert_eq!(stats.reports, 1);
+        }
+        Err(_panic_cause) => assert!(!is_evidence_valid),
+    }
+}
+
+/// Tests if receipts signed by a node for different choices of the same request are not
+/// evidence of misbehavior.
+#[proptest]
+fn responses_to_different_choices_are_not_conflicting(
+    scheme: SignatureScheme,
+    node_secret: [u8; 32],
+    request_id: CryptoHash,
+    first_interaction: ChatInteraction,
+    mut second_interaction: ChatInteraction,
+    tokens: u64,
+) {
+    prop_assume!(first_interaction.choice_index != second_interaction.choice_index);
+
+    second_interaction.model = first_interaction.model.clone();
+    second_interaction.prompt = first_interaction.prompt.clone();
+
+    let node = TestNode::with_scheme(scheme, node_secret);
+    let [first, second] = [&first_interaction, &second_interaction].map(|interaction| {
+        let receipt = node.receipt(request_id, interaction, tokens);
+
+        SignedNodeResponse {
+            response: receipt.response(interaction.id(), interaction.choice_index),
+            signature: receipt.signature,
+        }
+    });
+    let evidence = NodeMisbehaviorEvidence::ConflictingResponses { first, second };
+
+    assert!(!evidence.proves_misbehavior_of(node.public_key()));
+
+    let result = panic::catch_unwind(move || {
+        let mut contract = setup_contract();
+
+        contract
+            .execute_message(Message::SuspendNode {
+                node: node.public_key(),
+                evidence,
+            })
+            .blocking_wait();
+    });
+
+    assert!(result.is_err());
+}
+
+/// Tests if node reward claims are sent to the chain where the application was created.
+#[proptest]
+fn node_reward_claims_are_sent_to_creation_chain(
+    creator_chain_id: ChainId,
+    node: PublicKey,
+    destination_chain_id: ChainId,
+    destination_owner: AccountOwner,
+    signature: Signature,
+) {
+    let destination = Account::new(destination_chain_id, destination_owner);
+    let mut contract = setup_contract();
+
+    contract
+        .runtime
+        .set_application_creator_chain_id(creator_chain_id);
+
+    contract
+        .execute_operation(Operation::ClaimNodeRewards {
+            node,
+            destination,
+            signature: signature.clone(),
+        })
+        .blocking_wait();
+
+    let messages = contract.runtime.created_send_message_requests();
+
+    assert_eq!(messages.len(), 1);
+    assert_eq!(
+        messages[0],
+        SendMessageRequest {
+            destination: Destination::Recipient(creator_chain_id),
+            authenticated: false,
+            is_tracked: false,
+            grant: Resources::default(),
+            message: Message::PayNodeRewards {
+                node,
+                destination,
+                signature,
+            },
+        }
+    );
+}
+
+/// Tests if the unclaimed rewards of a node are paid out to the account designated by the node,
+/// unless the node was suspended.
+#[proptest]
+fn node_rewards_are_paid_out(
+    application_id: ApplicationId<atoma_demo::ApplicationAbi>,
+    creator_chain_id: ChainId,
+    node_secret: [u8; 32],
+    other_secret: [u8; 32],
+    destination_chain_id: ChainId,
+    destination_owner: AccountOwner,
+    #[strategy(0..=u128::from(u64::MAX))] unclaimed_rewards_attos: u128,
+    #[strategy(0..=u128::from(u64::MAX))] claimed_rewards_attos: u128,
+    verified_interactions: u64,
+    tokens_served: u64,
+    suspension: Option<NodeSuspension>,
+    is_signed_by_node: bool,
+) {
+    prop_assume!(destination_chain_id != creator_chain_id);
+    prop_assume!(node_secret != other_secret);
+
+    let node = TestNode::new(node_secret).public_key();
+    let destination = Account::new(destination_chain_id, destination_owner);
+    let application_owner = AccountOwner::from(application_id.forget_abi());
+    let unclaimed_rewards = Amount::from_attos(unclaimed_rewards_attos);
+    let initial_stats = NodeStats {
+        verified_interactions,
+        tokens_served,
+        unclaimed_rewards,
+        claimed_rewards: Amount::from_attos(claimed_rewards_attos),
+        ..NodeStats::default()
+    };
+    let is_claim_valid = is_signed_by_node && suspension.is_none();
+
+    let result = panic::catch_unwind(move || {
+        let signer = if is_signed_by_node {
+            TestNode::new(node_secret)
+        } else {
+            TestNode::new(other_secret)
+        };
+        let mut contract = setup_contract();
+
+        contract
+            .runtime
+            .set_application_id(application_id)
+            .set_chain_id(creator_chain_id)
+            .set_owner_balance(application_owner, unclaimed_rewards);
+
+        contract
+            .state
+            .node_stats
+            .insert(&node, initial_stats)
+            .expect("Failed to store node statistics");
+
+        if let Some(suspension) = suspension {
+            contract
+                .state
+                .suspended_atoma_nodes
+                .insert(&node, suspension)
+                .expect("Failed to suspend an Atoma node");
+        }
+
+        contract
+            .execute_message(Message::PayNodeRewards {
+                node,
+                destination,
+                signature: signer.sign(CryptoHash::new(&NodeRewardClaim { node, destination })),
+            })
+            .blocking_wait();
+
+        contract
+    });
+
+    match result {
+        Ok(mut contract) => {
+            assert!(is_claim_valid);
+
+            let stats = contract
+                .state
+                .node_stats
+                .get(&node)
+                .blocking_wait()
+                .expect("Failed to read node statistics from the state");
+            let expected_stats = NodeStats {
+                unclaimed_rewards: Amount::ZERO,
+                claimed_rewards: initial_stats
+                    .claimed_rewards
+                    .saturating_add(unclaimed_rewards),
+                ..initial_stats
+            };
+            let expected_transfers = iter::once((destination, unclaimed_rewards))
+                .filter(|(_, amount)| *amount > Amount::ZERO)
+                .collect::<HashMap<_, _>>();
+
+            assert_eq!(stats, Some(expected_stats));
+            assert_eq!(contract.runtime.outgoing_transfers(), &expected_transfers);
+            assert_eq!(
+                contract.runtime.owner_balance(application_owner),
+                Amount::ZERO
+            );
+        }
+        Err(_panic_cause) => assert!(!is_claim_valid),
+    }
+}
+
+/// Tests if node attestation can only be configured in the chain where the application was
+/// created.
+#[proptest]
+fn only_creation_chain_can_configure_attestation(
+    creator_chain_id: ChainId,
+    chain_id: ChainId,
+    policy: Option<AttestationPolicy>,
+) {
+    let expected_policy = policy.clone();
+
+    let result = panic::catch_unwind(move || {
+        let mut contract = setup_contract();
+
+        contract
+            .runtime
+            .set_application_creator_chain_id(creator_chain_id)
+            .set_chain_id(chain_id);
+
+        contract
+            .execute_operation(Operation::ConfigureAttestation { policy })
+            .blocking_wait();
+
+        contract
+    });
+
+    match result {
+        Ok(contract) => {
+            assert_eq!(chain_id, creator_chain_id);
+            assert_eq!(*contract.state.attestation_policy.get(), expected_policy);
+        }
+        Err(_panic_cause) => assert_ne!(chain_id, creator_chain_id),
+    }
+}
+
+/// Tests if attestation reports are sent to the chain where the application was created.
+#[proptest]
+fn attestation_registrations_are_sent_to_creation_chain(
+    creator_chain_id: ChainId,
+    report: AttestationReport,
+) {
+    let mut contract = setup_contract();
+
+    contract
+        .runtime
+        .set_application_creator_chain_id(creator_chain_id);
+
+    contract
+        .execute_operation(Operation::RegisterAttestation {
+            report: report.clone(),
+        })
+        .blocking_wait();
+
+    let messages = contract.runtime.created_send_message_requests();
+
+    assert_eq!(messages.len(), 1);
+    assert_eq!(
+        messages[0],
+        SendMessageRequest {
+            destination: Destination::Recipient(creator_chain_id),
+            authenticated: false,
+            is_tracked: false,
+            grant: Resources::default(),
+            message: Message::VerifyAttestation(report),
+        }
+    );
+}
+
+/// Tests if node attestations are only registered if their report is signed by a trusted
+/// attester for an enclave with an expected measurement.
+#[proptest]
+fn attestation_reports_are_verified(
+    node: PublicKey,
+    enclave: EnclaveMeasurement,
+    attester_scheme: SignatureScheme,
+    attester_secret: [u8; 32],
+    other_secret: [u8; 32],
+    mut policy: Option<AttestationPolicy>,
+    attested_at: BlockTime,
+    is_trusted_attester: bool,
+    is_expected_measurement: bool,
+    is_signed_by_attester: bool,
+) {
+    prop_assume!(attester_secret != other_secret);
+
+    let attester = TestNode::with_scheme(attester_scheme, attester_secret);
+    let signer = if is_signed_by_attester {
+        TestNode::with_scheme(attester_scheme, attester_secret)
+    } else {
+        TestNode::with_scheme(attester_scheme, other_secret)
+    };
+    let mut report = signer.attest(node, enclave);
+
+    report.attester = attester.public_key();
+
+    if let Some(policy) = &mut policy {
+        policy.attesters.retain(|key| *key != attester.public_key());
+        policy
+            .measurements
+            .retain(|measurement| *measurement != enclave);
+
+        if is_trusted_attester {
+            policy.attesters.push(attester.public_key());
+        }
+
+        if is_expected_measurement {
+            policy.measurements.push(enclave);
+        }
+    }
+
+    let is_report_valid =
+        policy.is_some() && is_trusted_attester && is_expected_measurement && is_signed_by_attester;
+
+    let result = panic::catch_unwind(move || {
+        let mut contract = setup_contract();
+
+        contract
+            .runtime
+            .set_system_time(attested_at.timestamp)
+            .set_block_height(attested_at.block_height);
+
+        contract.state.attestation_policy.set(policy);
+
+        contract
+            .execute_message(Message::VerifyAttestation(report))
+            .blocking_wait();
+
+        contract
+    });
+
+    match result {
+        Ok(contract) => {
+            assert!(is_report_valid);
+
+            let attestation = contract
+                .state
+                .attested_atoma_nodes
+                .get(&node)
+                .blocking_wait()
+                .expect("Failed to read node attestations from the state");
+
+            assert_eq!(
+                attestation,
+                Some(NodeAttestation {
+                    enclave,
+                    attester: attester.public_key(),
+                    attested_at,
+                })
+            );
+        }
+        Err(_panic_cause) => assert!(!is_report_valid),
+    }
+}
+
+/// Tests if node receipts are only accepted from nodes with a trusted attestation when the
+/// attestation policy requires it, and if the attestation can't be bypassed with an empty
+/// quorum.
+#[proptest]
+fn attestation_is_required_for_node_receipts(
+    requester_message_id: MessageId,
+    node_secret: [u8; 32],
+    interaction: ChatInteraction,
+    request_id: CryptoHash,
+    tokens: u64,
+    requested_at: BlockTime,
+    verified_at: BlockTime,
+    mut policy: AttestationPolicy,
+    attestation: Option<NodeAttestation>,
+    is_attestation_trusted: bool,
+    has_receipt: bool,
+    #[strategy(0..=1_u32)] quorum: u32,
+) {
+    if let Some(attestation) = attestation {
+        policy
+            .attesters
+            .retain(|attester| *attester != attestation.attester);
+        policy
+            .measurements
+            .retain(|measurement| *measurement != attestation.enclave);
+
+        if is_attestation_trusted {
+            policy.attesters.push(attestation.attester);
+            policy.measurements.push(attestation.enclave);
+        }
+    }
+
+    let is_accepted = if policy.is_required {
+        quorum > 0 && has_receipt && attestation.is_some() && is_attestation_trusted
+    } else {
+        has_receipt || quorum == 0
+    };
+
+    let result = panic::catch_unwind(move || {
+        let node = TestNode::new(node_secret);
+        let mut contract = setup_contract();
+
+        contract
+            .runtime
+            .set_message_id(requester_message_id)
+            .set_authenticated_signer(None)
+            .set_system_time(verified_at.timestamp)
+            .set_block_height(verified_at.block_height);
+
+        contract
+            .state
+            .allowed_models
+            .insert(&interaction.model)
+            .expect("Failed to add a model to the set of allowed models");
+        contract
+            .state
+            .active_atoma_nodes
+            .insert(&node.public_key())
+            .expect("Failed to add a node to the set of active Atoma nodes");
+        contract.state.attestation_policy.set(Some(policy));
+
+        if let Some(attestation) = attestation {
+            contract
+                .state
+                .attested_atoma_nodes
+                .insert(&node.public_key(), attestation)
+                .expect("Failed to register the attestation of an Atoma node");
+        }
+
+        let receipts = if has_receipt {
+            vec![node.receipt(request_id, &interaction, tokens)]
+        } else {
+            vec![]
+        };
+
+        contract
+            .execute_message(Message::VerifySignature {
+                interaction,
+                requested_at,
+                payment: Amount::ZERO,
+                receipts,
+                quorum,
+            })
+            .blocking_wait();
+    });
+
+    assert_eq!(result.is_ok(), is_accepted);
+}
+
+/// Tests if chat interactions are logged on chain.
+#[proptest]
+fn verified_chat_interactions_are_logged_on_chain(interactions: Vec<LoggedChatInteraction>) {
+    let mut contract = setup_contract();
+
+    for interaction in interactions.clone() {
+        contract
+            .execute_message(Message::LogVerifiedChatInteraction(interaction))
+            .blocking_wait();
+    }
+
+    let logged_interactions = contract
+        .state
+        .chat_log
+        .index_values()
+        .blocking_wait()
+        .expect("Failed to read logged chat interactions from the state")
+        .into_iter()
+        .collect::<BTreeMap<_, _>>();
+
+    let mut expected_merkle_range = MerkleMountainRange::default();
+    for interaction in &interactions {
+        expected_merkle_range.push(CryptoHash::new(interaction));
+    }
+
+    let expected_entries = interactions
+        .into_iter()
+        .map(ChatLogEntry::Logged)
+        .enumerate()
+        .map(|(index, entry)| (index as u64, entry))
+        .collect::<BTreeMap<_, _>>();
+
+    assert_eq!(logged_interactions, expected_entries);
+    assert_eq!(
+        contract.state.chat_log_merkle_range.get(),
+        &expected_merkle_range
+    );
+}
+
+/// Tests if the oldest entries of the chat log can only be pruned by the chain's owners.
+#[proptest]
+fn chat_log_is_pruned_by_owners(
+    interactions: Vec<LoggedChatInteraction>,
+    #[strategy(proptest::option::of(any::<u64>().prop_map(Timestamp::from)))] older_than: Option<
+        Timestamp,
+    >,
+    #[strategy(proptest::option::of(0..10_u64))] max_count: Option<u64>,
+    keep_digest: bool,
+    owner: AccountOwner,
+    signer: Option<AccountOwner>,
+) {
+    let result = panic::catch_unwind({
+        let interactions = interactions.clone();
+
+        move || {
+            let mut contract = setup_contract();
+
+            contract
+                .runtime
+                .set_chain_ownership(ChainOwnership::single(owner))
+                .set_authenticated_signer(signer);
+
+            for interaction in interactions {
+                contract
+                    .execute_message(Message::LogVerifiedChatInteraction(interaction))
+                    .blocking_wait();
+            }
+
+            contract
+                .execute_operation(Operation::PruneChatLog {
+                    older_than,
+                    max_count,
+                    keep_digest,
+                })
+                .blocking_wait();
+
+            contract
+        }
+    });
+
+    match result {
+        Ok(contract) => {
+            assert_eq!(signer, Some(owner));
+
+            let entries = interactions
+                .into_iter()
+                .map(ChatLogEntry::Logged)
+                .collect::<Vec<_>>();
+            let pruned_count = count_entries_to_prune(&entries, older_than, max_count);
+            let expected_pruned_ranges = if keep_digest && pruned_count > 0 {
+                vec![PrunedChatLogRange::new(0, &entries[..pruned_count])]
+            } else {
+                vec![]
+            };
+
+            check_chat_log(
+                &contract,
+                &entries[pruned_count..],
+                pruned_count,
+                &expected_pruned_ranges,
+            );
+        }
+        Err(_panic_cause) => assert_ne!(signer, Some(owner)),
+    }
+}
+
+/// Tests if the chat log's retention policy can only be configured by the chain's owners, and
+/// is applied whenever an entry is logged.
+#[proptest]
+fn chat_log_retention_is_applied(
+    interactions: Vec<LoggedChatInteraction>,
+    #[strategy(any::<u64>().prop_map(Timestamp::from))] now: Timestamp,
+    #[strategy(proptest::option::of(any::<u64>().prop_map(TimeDelta::from_micros)))]
+    max_age: Option<TimeDelta>,
+    #[strategy(proptest::option::of(0..10_u64))] max_count: Option<u64>,
+    keep_digest: bool,
+    owner: AccountOwner,
+    signer: Option<AccountOwner>,
+) {
+    let result = panic::catch_unwind({
+        let interactions = interactions.clone();
+
+        move || {
+            let mut contract = setup_contract();
+
+            contract
+                .runtime
+                .set_chain_ownership(ChainOwnership::single(owner))
+                .set_authenticated_signer(signer)
+                .set_system_time(now);
+
+            contract
+                .execute_operation(Operation::ConfigureChatLogRetention {
+                    retention: Some(ChatLogRetention {
+                        max_age,
+                        max_count,
+                        keep_digest,
+                    }),
+                })
+                .blocking_wait();
+
+            // The retention policy is applied without the owners' authentication
+            contract.runtime.set_authenticated_signer(None);
+
+            for interaction in interactions {
+                contract
+                    .execute_message(Message::LogVerifiedChatInteraction(interaction))
+                    .blocking_wait();
+            }
+
+            contract
+        }
+    });
+
+    match result {
+        Ok(contract) => {
+            assert_eq!(signer, Some(owner));
+
+            let older_than = max_age.map(|max_age| now.saturating_sub(max_age));
+            let mut expected_entries = Vec::new();
+            let mut expected_pruned_count = 0;
+            let mut expected_pruned_ranges = Vec::new();
+
+            for interaction in interactions {
+                expected_entries.push(ChatLogEntry::Logged(interaction));
+
+                let pruned_count = count_entries_to_prune(&expected_entries, older_than, max_count);
+
+                if pruned_count > 0 {
+                    if keep_digest {
+                        expected_pruned_ranges.push(PrunedChatLogRange::new(
+                            expected_pruned_count as u64,
+                            &expected_entries[..pruned_count],
+                        ));
+                    }
+
+                    expected_entries.drain(..pruned_count);
+                    expected_pruned_count += pruned_count;
+                }
+            }
+
+            check_chat_log(
+                &contract,
+                &expected_entries,
+                expected_pruned_count,
+                &expected_pruned_ranges,
+            );
+        }
+        Err(_panic_cause) => assert_ne!(signer, Some(owner)),
+    }
+}
+
+/// Tests if chat log entries can only be redacted by the chain's owners, keeping their hash and
+/// metadata.
+#[proptest]
+fn chat_log_entries_are_redacted_by_owners(
+    #[any(size_range(1..10).lift())] interactions: Vec<LoggedChatInteraction>,
+    #[strategy(0..#interactions.len())] index: usize,
+    owner: AccountOwner,
+    signer: Option<AccountOwner>,
+    redacted_at: BlockTime,
+) {
+    let result = panic::catch_unwind({
+        let interactions = interactions.clone();
 
-/// Tests if nodes can be added to and removed from the set of active Atoma nodes.
+        move || {
+            let mut contract = setup_contract();
+
+            contract
+                .runtime
+                .set_chain_ownership(ChainOwnership::single(owner))
+                .set_authenticated_signer(signer)
+                .set_system_time(redacted_at.timestamp)
+                .set_block_height(redacted_at.block_height);
+
+            for interaction in interactions {
+                contract
+                    .execute_message(Message::LogVerifiedChatInteraction(interaction))
+                    .blocking_wait();
+            }
+
+            contract
+                .execute_operation(Operation::RedactChatLogEntry {
+                    index: index as u64,
+                })
+                .blocking_wait();
+
+            contract
+        }
+    });
+
+    match result {
+        Ok(contract) => {
+            assert_eq!(signer, Some(owner));
+
+            let mut expected_entries = interactions
+                .into_iter()
+                .map(ChatLogEntry::Logged)
+                .collect::<Vec<_>>();
+            let ChatLogEntry::Logged(redacted_interaction) = &expected_entries[index] else {
+                unreachable!("All entries were logged without redactions");
+            };
+            let redacted_entry = ChatLogEntry::Redacted(RedactedChatInteraction::new(
+                redacted_interaction,
+                redacted_at,
+            ));
+
+            assert_eq!(redacted_entry.hash(), expected_entries[index].hash());
+
+            expected_entries[index] = redacted_entry;
+
+            check_chat_log(&contract, &expected_entries, 0, &[]);
+        }
+        Err(_panic_cause) => assert_ne!(signer, Some(owner)),
+    }
+}
+
+/// Tests if pruned chat log entries can't be redacted, and if redactions only replace the entry
+/// stored under their index.
 #[proptest]
-fn updating_nodes(
-    application_id: ApplicationId<atoma_demo::ApplicationAbi>,
-    creator_chain_id: ChainId,
-    test_operations: TestUpdateNodesOperations,
+fn redactions_respect_pruned_chat_log_entries(
+    #[any(size_range(2..10).lift())] interactions: Vec<LoggedChatInteraction>,
+    #[strategy(1..#interactions.len())] pruned_count: usize,
+    #[strategy(0..#interactions.len())] index: usize,
+    owner: AccountOwner,
+    redacted_at: BlockTime,
 ) {
-    let mut test = NodeSetTest::new(application_id, creator_chain_id);
+    let result = panic::catch_unwind({
+        let interactions = interactions.clone();
 
-    for test_operation in test_operations.0 {
-        let operation = test.prepare_operation(test_operation);
+        move || {
+            let mut contract = setup_contract();
 
-        test.contract.execute_operation(operation).blocking_wait();
+            contract
+                .runtime
+                .set_chain_ownership(ChainOwnership::single(owner))
+                .set_authenticated_signer(Some(owner))
+                .set_system_time(redacted_at.timestamp)
+                .set_block_height(redacted_at.block_height);
 
-        test.check_active_atoma_nodes();
+            let retained_count = (interactions.len() - pruned_count) as u64;
+
+            for interaction in interactions {
+                contract
+                    .execute_message(Message::LogVerifiedChatInteraction(interaction))
+                    .blocking_wait();
+            }
+
+            contract
+                .execute_operation(Operation::PruneChatLog {
+                    older_than: None,
+                    max_count: Some(retained_count),
+                    keep_digest: false,
+                })
+                .blocking_wait();
+
+            contract
+                .execute_operation(Operation::RedactChatLogEntry {
+                    index: index as u64,
+                })
+                .blocking_wait();
+
+            contract
+        }
+    });
+
+    match result {
+        Ok(contract) => {
+            assert!(index >= pruned_count);
+
+            let mut expected_entries = interactions[pruned_count..]
+                .iter()
+                .cloned()
+                .map(ChatLogEntry::Logged)
+                .collect::<Vec<_>>();
+            let ChatLogEntry::Logged(redacted_interaction) =
+                &expected_entries[index - pruned_count]
+            else {
+                unreachable!("All entries were logged without redactions");
+            };
+
+            expected_entries[index - pruned_count] = ChatLogEntry::Redacted(
+                RedactedChatInteraction::new(redacted_interaction, redacted_at),
+            );
+
+            check_chat_log(&contract, &expected_entries, pruned_count, &[]);
+        }
+        Err(_panic_cause) => assert!(index < pruned_count),
     }
 }
 
-/// Tests if the set of active Atoma nodes can only be changed in the chain where the application
-/// was created.
+/// Tests if embeddings are requested to be verified.
 #[proptest]
-fn only_creation_chain_can_track_nodes(
+fn embedding_is_requested_to_be_verified(
     application_id: ApplicationId<atoma_demo::ApplicationAbi>,
     creator_chain_id: ChainId,
-    chain_id: ChainId,
-    test_operation: TestUpdateNodesOperation,
+    embedding: EmbeddingRecord,
+    #[any(size_range(0..3).lift())] receipts: Vec<NodeReceipt>,
+    quorum: u32,
+) {
+    let mut contract = setup_contract();
+
+    contract
+        .runtime
+        .set_application_id(application_id)
+        .set_application_creator_chain_id(creator_chain_id);
+
+    contract
+        .execute_operation(Operation::LogEmbedding {
+            embedding: embedding.clone(),
+            receipts: receipts.clone(),
+            quorum,
+        })
+        .blocking_wait();
+
+    let messages = contract.runtime.created_send_message_requests();
+
+    assert_eq!(messages.len(), 1);
+    assert_eq!(
+        messages[0],
+        SendMessageRequest {
+            destination: Destination::Recipient(creator_chain_id),
+            authenticated: false,
+            is_tracked: false,
+            grant: Resources::default(),
+            message: Message::VerifyEmbedding {
+                embedding,
+                receipts,
+                quorum,
+            },
+        }
+    );
+}
+
+/// Tests if embeddings are only approved if their vector has the declared number of
+/// dimensions.
+#[proptest]
+fn embedding_dimensions_are_verified(
+    requester_message_id: MessageId,
+    mut embedding: EmbeddingRecord,
+    dimensions: u32,
 ) {
+    embedding.dimensions = dimensions;
+    let has_correct_dimensions = usize::try_from(dimensions) == Ok(embedding.vector.len());
+
     let result = panic::catch_unwind(move || {
-        let mut test = NodeSetTest::new(application_id, creator_chain_id).with_chain_id(chain_id);
-        let operation = test.prepare_operation(test_operation);
+        let mut contract = setup_contract();
 
-        test.contract.execute_operation(operation).blocking_wait();
+        contract.runtime.set_message_id(requester_message_id);
 
-        test
+        contract
+            .state
+            .allowed_models
+            .insert(&embedding.model)
+            .expect("Failed to add a model to the set of allowed models");
+
+        contract
+            .execute_message(Message::VerifyEmbedding {
+                embedding: embedding.clone(),
+                receipts: vec![],
+                quorum: 0,
+            })
+            .blocking_wait();
+
+        (contract, embedding)
     });
 
     match result {
-        Ok(test) => {
+        Ok((contract, embedding)) => {
+            assert!(has_correct_dimensions);
+
+            let messages = contract.runtime.created_send_message_requests();
+
+            assert_eq!(messages.len(), 1);
             assert_eq!(
-                chain_id, creator_chain_id,
-                "Contract executed `Operation::UpdateNodes` \
-                outside of the application's creation chain"
+                messages[0].destination,
+                Destination::Recipient(requester_message_id.chain_id)
             );
-            test.check_active_atoma_nodes();
-        }
-        Err(_panic_cause) => {
-            assert_ne!(
-                chain_id, creator_chain_id,
-                "Contract failed to execute `Operation::UpdateNodes` \
-                on the application's creation chain"
+            assert_eq!(
+                messages[0].message,
+                Message::LogVerifiedEmbedding(embedding)
             );
         }
+        Err(_panic_cause) => assert!(!has_correct_dimensions),
     }
 }
 
-/// Tests if the contract rejects adding a node twice.
+/// Tests if embeddings are only approved if they were computed by an allowed model, and are
+/// attested by a quorum of active Atoma nodes.
 #[proptest]
-fn cant_add_and_remove_node_in_the_same_operation(
-    application_id: ApplicationId<atoma_demo::ApplicationAbi>,
-    creator_chain_id: ChainId,
-    #[any(size_range(1..5).lift())] conflicting_nodes: HashSet<PublicKey>,
-    mut test_operation: TestUpdateNodesOperation,
+fn embedding_receipts_are_verified(
+    requester_message_id: MessageId,
+    node_secret: [u8; 32],
+    embedding: EmbeddingRecord,
+    request_id: CryptoHash,
+    tokens: u64,
+    #[strategy(0..=2_u32)] quorum: u32,
+    is_allowed_model: bool,
+    is_active_node: bool,
+    is_signature_valid: bool,
 ) {
+    let is_approved = is_allowed_model && is_active_node && is_signature_valid && quorum <= 1;
+
     let result = panic::catch_unwind(move || {
-        let mut test = NodeSetTest::new(application_id, creator_chain_id);
+        let node = TestNode::new(node_secret);
+        let mut receipt = node.record_receipt(request_id, embedding.id(), tokens);
+        let mut contract = setup_contract();
 
-        test_operation.add.extend(conflicting_nodes.iter().copied());
-        test_operation.remove.extend(conflicting_nodes);
+        if !is_signature_valid {
+            receipt.tokens = tokens.wrapping_add(1);
+        }
 
-        let operation = test.prepare_operation(test_operation);
+        contract.runtime.set_message_id(requester_message_id);
 
-        test.contract.execute_operation(operation).blocking_wait();
+        if is_allowed_model {
+            contract
+                .state
+                .allowed_models
+                .insert(&embedding.model)
+                .expect("Failed to add a model to the set of allowed models");
+        }
+
+        if is_active_node {
+            contract
+                .state
+                .active_atoma_nodes
+                .insert(&node.public_key())
+                .expect("Failed to add a node to the set of active Atoma nodes");
+        }
+
+        contract
+            .execute_message(Message::VerifyEmbedding {
+                embedding,
+                receipts: vec![receipt],
+                quorum,
+            })
+            .blocking_wait();
     });
 
-    assert!(result.is_err());
+    assert_eq!(result.is_ok(), is_approved);
 }
 
-/// Tests if chat interactions are requested to be verified.
+/// Tests if verified embeddings are logged on chain.
 #[proptest]
-fn chat_interaction_is_requested_to_be_verified(
+fn verified_embeddings_are_logged_on_chain(embeddings: Vec<EmbeddingRecord>) {
+    let mut contract = setup_contract();
+
+    for embedding in embeddings.clone() {
+        contract
+            .execute_message(Message::LogVerifiedEmbedding(embedding))
+            .blocking_wait();
+    }
+
+    let logged_embeddings = contract
+        .state
+        .embedding_log
+        .read(..)
+        .blocking_wait()
+        .expect("Failed to read logged embeddings from the state");
+
+    assert_eq!(logged_embeddings, embeddings);
+}
+
+/// Tests if image generations are requested to be verified.
+#[proptest]
+fn image_generation_is_requested_to_be_verified(
     application_id: ApplicationId<atoma_demo::ApplicationAbi>,
     creator_chain_id: ChainId,
-    interaction: ChatInteraction,
+    generation: ImageGenerationRecord,
+    #[any(size_range(0..3).lift())] receipts: Vec<NodeReceipt>,
+    quorum: u32,
 ) {
     let mut contract = setup_contract();
 
@@ -114,8 +2167,10 @@ fn chat_interaction_is_requested_to_be_verified(
         .set_application_creator_chain_id(creator_chain_id);
 
     contract
-        .execute_operation(Operation::LogChatInteraction {
-            interaction: interaction.clone(),
+        .execute_operation(Operation::LogImageGeneration {
+            generation: generation.clone(),
+            receipts: receipts.clone(),
+            quorum,
         })
         .blocking_wait();
 
@@ -129,30 +2184,141 @@ fn chat_interaction_is_requested_to_be_verified(
             authenticated: false,
             is_tracked: false,
             grant: Resources::default(),
-            message: Message::VerifySignature(interaction),
+            message: Message::VerifyImageGeneration {
+                generation,
+                receipts,
+                quorum,
+            },
         }
     );
 }
 
-/// Tests if chat interactions are logged on chain.
+/// Tests if image generations are only approved if they have at least one image.
+#[proptest]
+fn image_generations_must_have_images(
+    requester_message_id: MessageId,
+    generation: ImageGenerationRecord,
+) {
+    let has_images = !generation.images.is_empty();
+
+    let result = panic::catch_unwind(move || {
+        let mut contract = setup_contract();
+
+        contract.runtime.set_message_id(requester_message_id);
+
+        contract
+            .state
+            .allowed_models
+            .insert(&generation.model)
+            .expect("Failed to add a model to the set of allowed models");
+
+        contract
+            .execute_message(Message::VerifyImageGeneration {
+                generation: generation.clone(),
+                receipts: vec![],
+                quorum: 0,
+            })
+            .blocking_wait();
+
+        (contract, generation)
+    });
+
+    match result {
+        Ok((contract, generation)) => {
+            assert!(has_images);
+
+            let messages = contract.runtime.created_send_message_requests();
+
+            assert_eq!(messages.len(), 1);
+            assert_eq!(
+                messages[0].destination,
+                Destination::Recipient(requester_message_id.chain_id)
+            );
+            assert_eq!(
+                messages[0].message,
+                Message::LogVerifiedImageGeneration(generation)
+            );
+        }
+        Err(_panic_cause) => assert!(!has_images),
+    }
+}
+
+/// Tests if image generations are only approved if they were generated by an allowed model, and
+/// are attested by a quorum of active Atoma nodes.
+#[proptest]
+fn image_generation_receipts_are_verified(
+    requester_message_id: MessageId,
+    node_secret: [u8; 32],
+    generation: ImageGenerationRecord,
+    request_id: CryptoHash,
+    tokens: u64,
+    #[strategy(0..=2_u32)] quorum: u32,
+    is_allowed_model: bool,
+    is_active_node: bool,
+    is_signature_valid: bool,
+) {
+    prop_assume!(!generation.images.is_empty());
+
+    let is_approved = is_allowed_model && is_active_node && is_signature_valid && quorum <= 1;
+
+    let result = panic::catch_unwind(move || {
+        let node = TestNode::new(node_secret);
+        let mut receipt = node.record_receipt(request_id, generation.id(), tokens);
+        let mut contract = setup_contract();
+
+        if !is_signature_valid {
+            receipt.tokens = tokens.wrapping_add(1);
+        }
+
+        contract.runtime.set_message_id(requester_message_id);
+
+        if is_allowed_model {
+            contract
+                .state
+                .allowed_models
+                .insert(&generation.model)
+                .expect("Failed to add a model to the set of allowed models");
+        }
+
+        if is_active_node {
+            contract
+                .state
+                .active_atoma_nodes
+                .insert(&node.public_key())
+                .expect("Failed to add a node to the set of active Atoma nodes");
+        }
+
+        contract
+            .execute_message(Message::VerifyImageGeneration {
+                generation,
+                receipts: vec![receipt],
+                quorum,
+            })
+            .blocking_wait();
+    });
+
+    assert_eq!(result.is_ok(), is_approved);
+}
+
+/// Tests if verified image generations are logged on chain.
 #[proptest]
-fn verified_chat_interactions_are_logged_on_chain(interactions: Vec<ChatInteraction>) {
+fn verified_image_generations_are_logged_on_chain(generations: Vec<ImageGenerationRecord>) {
     let mut contract = setup_contract();
 
-    for interaction in interactions.clone() {
+    for generation in generations.clone() {
         contract
-            .execute_message(Message::LogVerifiedChatInteraction(interaction))
+            .execute_message(Message::LogVerifiedImageGeneration(generation))
             .blocking_wait();
     }
 
-    let logged_interactions = contract
+    let logged_generations = contract
         .state
-        .chat_log
+        .image_log
         .read(..)
         .blocking_wait()
-        .expect("Failed to read logged chat interactions from the state");
+        .expect("Failed to read logged image generations from the state");
 
-    assert_eq!(logged_interactions, interactions);
+    assert_eq!(logged_generations, generations);
 }
 
 /// Creates a [`ApplicationContract`] instance to be tested.
@@ -162,10 +2328,216 @@ fn setup_contract() -> ApplicationContract {
     ApplicationContract::load(runtime).blocking_wait()
 }
 
+/// Counts how many of the oldest `entries` should be pruned from the chat log if they were
+/// requested before `older_than` or if there are more than `max_count` entries.
+fn count_entries_to_prune(
+    entries: &[ChatLogEntry],
+    older_than: Option<Timestamp>,
+    max_count: Option<u64>,
+) -> usize {
+    entries
+        .iter()
+        .enumerate()
+        .take_while(|(index, entry)| {
+            let is_too_old =
+                older_than.is_some_and(|cutoff| entry.requested_at().timestamp < cutoff);
+            let is_in_excess =
+                max_count.is_some_and(|max_count| (entries.len() - index) as u64 > max_count);
+
+            is_too_old || is_in_excess
+        })
+        .count()
+}
+
+/// Checks if the chat log in the `contract`'s state has the `expected_entries` stored under their
+/// indices, after pruning `expected_pruned_count` entries summarized by the
+/// `expected_pruned_ranges`.
+fn check_chat_log(
+    contract: &ApplicationContract,
+    expected_entries: &[ChatLogEntry],
+    expected_pruned_count: usize,
+    expected_pruned_ranges: &[PrunedChatLogRange],
+) {
+    let entries = contract
+        .state
+        .chat_log
+        .index_values()
+        .blocking_wait()
+        .expect("Failed to read logged chat interactions from the state")
+        .into_iter()
+        .collect::<BTreeMap<_, _>>();
+    let expected_entries = expected_entries
+        .iter()
+        .cloned()
+        .enumerate()
+        .map(|(position, entry)| ((expected_pruned_count + position) as u64, entry))
+        .collect::<BTreeMap<_, _>>();
+    let pruned_ranges = contract
+        .state
+        .chat_log_pruned_ranges
+        .read(..)
+        .blocking_wait()
+        .expect("Failed to read pruned chat log ranges from the state");
+
+    assert_eq!(entries, expected_entries);
+    assert_eq!(
+        *contract.state.chat_log_pruned_count.get(),
+        expected_pruned_count as u64
+    );
+    assert_eq!(pruned_ranges, expected_pruned_ranges);
+}
+
+/// An Atoma node's key pair, used to sign receipts and reward claims in tests.
+enum TestNode {
+    Ed25519(SigningKey),
+    Secp256k1(k256::ecdsa::SigningKey),
+    Secp256r1(p256::ecdsa::SigningKey),
+}
+
+impl TestNode {
+    /// Creates a [`TestNode`] with the Ed25519 key pair derived from the `secret`.
+    fn new(secret: [u8; 32]) -> Self {
+        TestNode::with_scheme(SignatureScheme::Ed25519, secret)
+    }
+
+    /// Creates a [`TestNode`] with the key pair of the signature `scheme` derived from the
+    /// `secret`.
+    fn with_scheme(scheme: SignatureScheme, secret: [u8; 32]) -> Self {
+        match scheme {
+            SignatureScheme::Ed25519 => TestNode::Ed25519(SigningKey::from_bytes(&secret)),
+            SignatureScheme::Secp256k1 => TestNode::Secp256k1(
+                k256::ecdsa::SigningKey::from_bytes(&secret.into())
+                    .expect("Invalid secret for a secp256k1 key"),
+            ),
+            SignatureScheme::Secp256r1 => TestNode::Secp256r1(
+                p256::ecdsa::SigningKey::from_bytes(&secret.into())
+                    .expect("Invalid secret for a secp256r1 key"),
+            ),
+        }
+    }
+
+    /// Returns the node's [`PublicKey`].
+    fn public_key(&self) -> PublicKey {
+        match self {
+            TestNode::Ed25519(signing_key) => {
+                PublicKey::Ed25519(signing_key.verifying_key().to_bytes())
+            }
+            TestNode::Secp256k1(signing_key) => PublicKey::Secp256k1(
+                signing_key
+                    .verifying_key()
+                    .to_encoded_point(true)
+                    .as_bytes()
+                    .try_into()
+                    .expect("Compressed secp256k1 keys have 33 bytes"),
+            ),
+            TestNode::Secp256r1(signing_key) => PublicKey::Secp256r1(
+                signing_key
+                    .verifying_key()
+                    .to_encoded_point(true)
+                    .as_bytes()
+                    .try_into()
+                    .expect("Compressed secp256r1 keys have 33 bytes"),
+            ),
+        }
+    }
+
+    /// Signs the bytes of the `hash` with the node's key.
+    fn sign(&self, hash: CryptoHash) -> Signature {
+        let message = hash.as_bytes().as_slice();
+
+        match self {
+            TestNode::Ed25519(signing_key) => Signature::from(signing_key.sign(message).to_bytes()),
+            TestNode::Secp256k1(signing_key) => {
+                let signature: k256::ecdsa::Signature = signing_key.sign(message);
+                Signature::from(<[u8; 64]>::from(signature.to_bytes()))
+            }
+            TestNode::Secp256r1(signing_key) => {
+                let signature: p256::ecdsa::Signature = signing_key.sign(message);
+                Signature::from(<[u8; 64]>::from(signature.to_bytes()))
+            }
+        }
+    }
+
+    /// Creates an [`AttestationReport`] signed with this key as the attester, binding the `node`
+    /// to the `enclave`.
+    fn attest(&self, node: PublicKey, enclave: EnclaveMeasurement) -> AttestationReport {
+        let statement = AttestationStatement { node, enclave };
+
+        AttestationReport {
+            node,
+            enclave,
+            attester: self.public_key(),
+            signature: self.sign(CryptoHash::new(&statement)),
+        }
+    }
+
+    /// Signs the `response` with the node's key.
+    fn sign_response(&self, response: NodeResponse) -> SignedNodeResponse {
+        SignedNodeResponse {
+            response,
+            signature: self.sign(CryptoHash::new(&response)),
+        }
+    }
+
+    /// Creates a [`NodeReceipt`] for the node serving `tokens` to produce the `interaction` for
+    /// the request with `request_id`.
+    fn receipt(
+        &self,
+        request_id: CryptoHash,
+        interaction: &ChatInteraction,
+        tokens: u64,
+    ) -> NodeReceipt {
+        self.choice_receipt(
+            request_id,
+            interaction.id(),
+            interaction.choice_index,
+            tokens,
+        )
+    }
+
+    /// Creates a [`NodeReceipt`] for the node serving `tokens` to produce the logged record with
+    /// the `record_id`, which has a single response, for the request with `request_id`.
+    fn record_receipt(
+        &self,
+        request_id: CryptoHash,
+        record_id: CryptoHash,
+        tokens: u64,
+    ) -> NodeReceipt {
+        self.choice_receipt(request_id, record_id, 0, tokens)
+    }
+
+    /// Creates a [`NodeReceipt`] for the node serving `tokens` to produce the logged record with
+    /// the `record_id`, which holds the choice at `choice_index` of the responses to the request
+    /// with `request_id`.
+    fn choice_receipt(
+        &self,
+        request_id: CryptoHash,
+        record_id: CryptoHash,
+        choice_index: u32,
+        tokens: u64,
+    ) -> NodeReceipt {
+        let response = NodeResponse {
+            request_id,
+            choice_index,
+            interaction_id: record_id,
+            tokens,
+        };
+
+        NodeReceipt {
+            node: self.public_key(),
+            request_id,
+            tokens,
+            signature: self.sign(CryptoHash::new(&response)),
+        }
+    }
+}
+
 /// Helper type with shared code for active Atoma node set tests.
 pub struct NodeSetTest {
     contract: ApplicationContract,
+    now: Timestamp,
     expected_nodes: HashSet<PublicKey>,
+    expected_active_since: HashMap<PublicKey, Option<Timestamp>>,
 }
 
 impl NodeSetTest {
@@ -183,11 +2555,14 @@ impl NodeSetTest {
             .runtime
             .set_application_id(application_id)
             .set_chain_id(creator_chain_id)
-            .set_application_creator_chain_id(creator_chain_id);
+            .set_application_creator_chain_id(creator_chain_id)
+            .set_system_time(Timestamp::from(0));
 
         NodeSetTest {
             contract,
+            now: Timestamp::from(0),
             expected_nodes: HashSet::new(),
+            expected_active_since: HashMap::new(),
         }
     }
 
@@ -200,13 +2575,25 @@ impl NodeSetTest {
     /// Prepares an [`Operation::UpdateNodes`] based on the configured
     /// [`TestUpdateNodesOperation`].
     ///
-    /// Updates the expected active Atoma nodes state to reflect the execution of the operation.
+    /// Updates the expected active Atoma nodes state to reflect the execution of the operation,
+    /// which is executed one microsecond after the previous one.
     pub fn prepare_operation(&mut self, test_operation: TestUpdateNodesOperation) -> Operation {
         let nodes_to_add = test_operation.add;
         let nodes_to_remove = test_operation.remove;
 
+        self.now = self.now.saturating_add_micros(1);
+        self.contract.runtime.set_system_time(self.now);
+
         for node_to_remove in &nodes_to_remove {
             self.expected_nodes.remove(node_to_remove);
+            self.expected_active_since.insert(*node_to_remove, None);
+        }
+
+        for node_to_add in &nodes_to_add {
+            self.expected_active_since
+                .entry(*node_to_add)
+                .or_default()
+                .get_or_insert(self.now);
         }
 
         self.expected_nodes.extend(nodes_to_add.iter().copied());
@@ -243,6 +2630,21 @@ impl NodeSetTest {
 
         assert_eq!(node_count, self.expected_nodes.len());
         assert_eq!(active_nodes, self.expected_nodes);
+
+        for (node, expected_active_since) in &self.expected_active_since {
+            let stats = self
+                .contract
+                .state
+                .node_stats
+                .get(node)
+                .blocking_wait()
+                .expect("Failed to read node statistics from state");
+
+            assert_eq!(
+                stats.and_then(|stats| stats.active_since),
+                *expected_active_since
+            );
+        }
     }
 }
 
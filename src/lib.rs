@@ -1,7 +1,18 @@
 // Copyright (c) Zefchain Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
-use linera_sdk::linera_base_types::{ContractAbi, ServiceAbi};
+pub mod merkle;
+
+use k256::ecdsa::signature::Verifier;
+use linera_sdk::{
+    linera_base_types::{
+        Account, AccountOwner, Amount, BcsHashable, BlockHeight, ChainId, ContractAbi, CryptoHash,
+        ServiceAbi, TimeDelta, Timestamp,
+    },
+    DataBlobHash,
+};
+#[cfg(feature = "test")]
+use proptest::{arbitrary::any, strategy::Strategy};
 use serde::{Deserialize, Serialize};
 
 pub struct ApplicationAbi;
@@ -17,7 +28,7 @@ impl ServiceAbi for ApplicationAbi {
 }
 
 /// Operations that the contract can execute.
-#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 pub enum Operation {
     /// Update the set of active Atoma nodes.
     UpdateNodes {
@@ -25,28 +36,1020 @@ pub enum Operation {
         remove: Vec<PublicKey>,
     },
 
-    /// Log an interaction with the AI.
-    LogChatInteraction { interaction: ChatInteraction },
+    /// Update the set of models allowed to produce logged chat interactions.
+    UpdateModels {
+        add: Vec<String>,
+        remove: Vec<String>,
+    },
+
+    /// Enable or disable recording every verified chat interaction in the audit log of the chain
+    /// that created the application.
+    ConfigureAuditLog { enabled: bool },
+
+    /// Prune the entries of this chain's chat log that were requested before `older_than` or
+    /// that exceed the `max_count` most recent entries.
+    PruneChatLog {
+        older_than: Option<Timestamp>,
+        max_count: Option<u64>,
+        keep_digest: bool,
+    },
+
+    /// Redact the entry at `index` of this chain's chat log, keeping only its hash and metadata.
+    RedactChatLogEntry { index: u64 },
+
+    /// Configure the retention policy applied to this chain's chat log whenever an entry is
+    /// logged, or disable automatic pruning if `retention` is `None`.
+    ConfigureChatLogRetention { retention: Option<ChatLogRetention> },
+
+    /// Update the Atoma API tokens stored on this chain for use by its service.
+    UpdateApiTokens {
+        add: Vec<ApiToken>,
+        remove: Vec<String>,
+    },
+
+    /// Configure the fee charged for verifying each chat interaction, or stop charging fees if
+    /// `fee` is `None`.
+    ConfigureChatFee { fee: Option<ChatFee> },
+
+    /// Log an interaction with the AI, paying for its verification with the `payment` taken from
+    /// the chain's balance.
+    ///
+    /// The `receipts` from the Atoma nodes that attest to the response attribute the interaction
+    /// to those nodes so that they are rewarded for it. The interaction is only verified if at
+    /// least `quorum` different active nodes attest to it.
+    LogChatInteraction {
+        interaction: ChatInteraction,
+        payment: Amount,
+        receipts: Vec<NodeReceipt>,
+        quorum: u32,
+    },
+
+    /// Report that an Atoma `node` misbehaved, suspending it if the `evidence` proves it.
+    ReportNode {
+        node: PublicKey,
+        evidence: NodeMisbehaviorEvidence,
+    },
+
+    /// Claim the rewards accumulated by an Atoma `node`, paying them out to the `destination`
+    /// account designated by the node's `signature` over a [`NodeRewardClaim`].
+    ClaimNodeRewards {
+        node: PublicKey,
+        destination: Account,
+        signature: Signature,
+    },
+
+    /// Configure how Atoma nodes attest that they run in a trusted execution environment, or
+    /// stop accepting attestations if `policy` is `None`.
+    ConfigureAttestation { policy: Option<AttestationPolicy> },
+
+    /// Register an attestation `report` binding an Atoma node's key to a measured enclave.
+    RegisterAttestation { report: AttestationReport },
+
+    /// Log an embedding computed by the AI.
+    ///
+    /// The embedding is only verified if at least `quorum` different active nodes attest to it
+    /// with their `receipts`.
+    LogEmbedding {
+        embedding: EmbeddingRecord,
+        receipts: Vec<NodeReceipt>,
+        quorum: u32,
+    },
+
+    /// Log images generated by the AI.
+    ///
+    /// The image generation is only verified if at least `quorum` different active nodes attest
+    /// to it with their `receipts`.
+    LogImageGeneration {
+        generation: ImageGenerationRecord,
+        receipts: Vec<NodeReceipt>,
+        quorum: u32,
+    },
+}
+
+/// An Atoma API token, stored on chain so that it doesn't have to be sent with every GraphQL
+/// request.
+///
+/// Only the token sealed to an X25519 key is stored, because the operation and the chain's state
+/// are readable by anyone with access to the chain's blocks, such as the validators. The service
+/// unseals the token with the secret key provided in each request that uses it, so that key is
+/// exposed to whoever runs the service and should be dedicated to the token. It must never be the
+/// key that encrypted chat interactions are sealed to.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ApiToken {
+    /// The name used to reference the token.
+    pub name: String,
+    /// The X25519 public key dedicated to sealing this token.
+    pub key: [u8; 32],
+    /// The secret token, sealed to the `key`.
+    pub ciphertext: Vec<u8>,
 }
 
 /// A single interaction with the AI chat.
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize, async_graphql::SimpleObject)]
+#[graphql(complex)]
 #[cfg_attr(feature = "test", derive(test_strategy::Arbitrary))]
 pub struct ChatInteraction {
+    #[cfg_attr(feature = "test", strategy("[A-Za-z0-9./-]*"))]
+    pub model: String,
+    pub prompt: ChatContent,
+    pub response: ChatContent,
+    #[cfg_attr(feature = "test", any(proptest::sample::size_range(0..3).lift()))]
+    pub tool_calls: Vec<ToolCall>,
+    /// The index of the response among the choices returned by the AI.
+    pub choice_index: u32,
+}
+
+impl ChatInteraction {
+    /// Returns the ID of this interaction, which is the hash of its contents.
+    pub fn id(&self) -> CryptoHash {
+        CryptoHash::new(self)
+    }
+}
+
+#[async_graphql::ComplexObject]
+impl ChatInteraction {
+    /// The ID of this interaction, which is the hash of its contents.
+    #[graphql(name = "id")]
+    async fn graphql_id(&self) -> CryptoHash {
+        self.id()
+    }
+}
+
+impl BcsHashable<'_> for ChatInteraction {}
+
+/// A [`ChatInteraction`] logged on chain, attributed to who requested it to be logged.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize, async_graphql::SimpleObject)]
+#[cfg_attr(feature = "test", derive(test_strategy::Arbitrary))]
+pub struct LoggedChatInteraction {
+    #[graphql(flatten)]
+    pub interaction: ChatInteraction,
+    /// The signer of the block that requested the interaction to be logged, if any.
+    pub requester: Option<AccountOwner>,
+    /// The chain that requested the interaction to be logged.
+    pub requester_chain_id: ChainId,
+    /// When the interaction was requested to be logged, on the requester chain.
+    pub requested_at: BlockTime,
+    /// When the interaction was verified, on the chain that created the application.
+    pub verified_at: BlockTime,
+}
+
+impl BcsHashable<'_> for LoggedChatInteraction {}
+
+/// An entry in the chat log.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize, async_graphql::Union)]
+#[cfg_attr(feature = "test", derive(test_strategy::Arbitrary))]
+pub enum ChatLogEntry {
+    /// A logged chat interaction.
+    Logged(LoggedChatInteraction),
+    /// A logged chat interaction whose contents were redacted.
+    Redacted(RedactedChatInteraction),
+}
+
+impl ChatLogEntry {
+    /// Returns the hash of the entry as it was logged, even if it was later redacted.
+    pub fn hash(&self) -> CryptoHash {
+        match self {
+            ChatLogEntry::Logged(logged_interaction) => CryptoHash::new(logged_interaction),
+            ChatLogEntry::Redacted(redacted_interaction) => redacted_interaction.entry_hash,
+        }
+    }
+
+    /// Returns when the interaction was requested to be logged, on the requester chain.
+    pub fn requested_at(&self) -> BlockTime {
+        match self {
+            ChatLogEntry::Logged(logged_interaction) => logged_interaction.requested_at,
+            ChatLogEntry::Redacted(redacted_interaction) => redacted_interaction.requested_at,
+        }
+    }
+}
+
+/// A tombstone for a [`LoggedChatInteraction`] whose prompt, response and tool calls were
+/// removed from the chat log.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize, async_graphql::SimpleObject)]
+#[cfg_attr(feature = "test", derive(test_strategy::Arbitrary))]
+pub struct RedactedChatInteraction {
+    /// The ID of the redacted interaction.
+    pub interaction_id: CryptoHash,
+    /// The hash of the entry before it was redacted, which is still covered by the chat log's
+    /// Merkle root.
+    pub entry_hash: CryptoHash,
+    /// The model that produced the redacted interaction.
+    #[cfg_attr(feature = "test", strategy("[A-Za-z0-9./-]*"))]
+    pub model: String,
+    /// The signer of the block that requested the interaction to be logged, if any.
+    pub requester: Option<AccountOwner>,
+    /// The chain that requested the interaction to be logged.
+    pub requester_chain_id: ChainId,
+    /// When the interaction was requested to be logged, on the requester chain.
+    pub requested_at: BlockTime,
+    /// When the interaction was verified, on the chain that created the application.
+    pub verified_at: BlockTime,
+    /// When the interaction was redacted.
+    pub redacted_at: BlockTime,
+}
+
+impl RedactedChatInteraction {
+    /// Creates the tombstone for the `logged_interaction`, redacted at `redacted_at`.
+    pub fn new(logged_interaction: &LoggedChatInteraction, redacted_at: BlockTime) -> Self {
+        RedactedChatInteraction {
+            interaction_id: logged_interaction.interaction.id(),
+            entry_hash: CryptoHash::new(logged_interaction),
+            model: logged_interaction.interaction.model.clone(),
+            requester: logged_interaction.requester,
+            requester_chain_id: logged_interaction.requester_chain_id,
+            requested_at: logged_interaction.requested_at,
+            verified_at: logged_interaction.verified_at,
+            redacted_at,
+        }
+    }
+}
+
+/// An entry in the audit log of the chain that created the application, recording a verified
+/// [`ChatInteraction`] by its hash.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize, async_graphql::SimpleObject)]
+#[cfg_attr(feature = "test", derive(test_strategy::Arbitrary))]
+pub struct AuditLogEntry {
+    /// The ID of the verified interaction.
+    pub interaction_id: CryptoHash,
+    /// The signer of the block that requested the interaction to be logged, if any.
+    pub requester: Option<AccountOwner>,
+    /// The chain that requested the interaction to be logged.
+    pub requester_chain_id: ChainId,
+    /// When the interaction was requested to be logged, on the requester chain.
+    pub requested_at: BlockTime,
+    /// When the interaction was verified.
+    pub verified_at: BlockTime,
+}
+
+impl From<&LoggedChatInteraction> for AuditLogEntry {
+    fn from(logged_interaction: &LoggedChatInteraction) -> Self {
+        AuditLogEntry {
+            interaction_id: logged_interaction.interaction.id(),
+            requester: logged_interaction.requester,
+            requester_chain_id: logged_interaction.requester_chain_id,
+            requested_at: logged_interaction.requested_at,
+            verified_at: logged_interaction.verified_at,
+        }
+    }
+}
+
+/// The fee charged for verifying each chat interaction.
+#[derive(
+    Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize, async_graphql::SimpleObject,
+)]
+pub struct ChatFee {
+    /// The amount of native tokens charged per interaction.
+    pub amount: Amount,
+    /// The account that receives the fees.
+    pub treasury: Account,
+    /// The part of the `amount` kept in the application's fee pool as a reward for the Atoma
+    /// nodes that attest to the interaction with a [`NodeReceipt`], split evenly between them.
+    pub node_reward: Amount,
+}
+
+/// A receipt signed by an Atoma node that produced a response logged on chain, such as the
+/// response of a [`ChatInteraction`], an [`EmbeddingRecord`] or an [`ImageGenerationRecord`].
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize, async_graphql::InputObject)]
+#[cfg_attr(feature = "test", derive(test_strategy::Arbitrary))]
+pub struct NodeReceipt {
+    /// The node that produced the response.
+    pub node: PublicKey,
+    /// The unique ID of the request served by the node.
+    pub request_id: CryptoHash,
+    /// The number of tokens served by the node to produce the response.
+    ///
+    /// GraphQL integers are signed 64-bit values, so receipts can only be provided through the
+    /// service's GraphQL API if they serve at most `i64::MAX` tokens.
+    pub tokens: u64,
+    /// The node's signature over the hash of the [`NodeReceipt::response`].
+    pub signature: Signature,
+}
+
+impl NodeReceipt {
+    /// Returns the [`NodeResponse`] signed by the node for the logged record with the
+    /// `record_id`, which holds the choice at `choice_index` of the request's responses.
+    pub fn response(&self, record_id: CryptoHash, choice_index: u32) -> NodeResponse {
+        NodeResponse {
+            request_id: self.request_id,
+            choice_index,
+            interaction_id: record_id,
+            tokens: self.tokens,
+        }
+    }
+
+    /// Checks if the receipt was signed by its node for the logged record with the `record_id`,
+    /// which holds the choice at `choice_index` of the request's responses.
+    pub fn verify(&self, record_id: CryptoHash, choice_index: u32) -> bool {
+        self.node.verify(
+            CryptoHash::new(&self.response(record_id, choice_index)),
+            &self.signature,
+        )
+    }
+}
+
+/// The response to a request served by an Atoma node, whose hash is signed by the node to vouch
+/// for it.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[cfg_attr(feature = "test", derive(test_strategy::Arbitrary))]
+pub struct NodeResponse {
+    /// The unique ID of the request served by the node.
+    pub request_id: CryptoHash,
+    /// The index of the response among the choices returned for the request, which is always
+    /// zero for records with a single response, such as embeddings and image generations.
+    pub choice_index: u32,
+    /// The ID of the logged chat interaction or record with the response.
+    pub interaction_id: CryptoHash,
+    /// The number of tokens served by the node to produce the response.
+    pub tokens: u64,
+}
+
+impl BcsHashable<'_> for NodeResponse {}
+
+/// A [`NodeResponse`] together with the signature of the node that served it.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[cfg_attr(feature = "test", derive(test_strategy::Arbitrary))]
+pub struct SignedNodeResponse {
+    /// The response served by the node.
+    pub response: NodeResponse,
+    /// The node's signature over the hash of the `response`.
+    pub signature: Signature,
+}
+
+impl SignedNodeResponse {
+    /// Checks if the response was signed by the `node`.
+    pub fn verify(&self, node: PublicKey) -> bool {
+        node.verify(CryptoHash::new(&self.response), &self.signature)
+    }
+}
+
+/// Evidence that an Atoma node misbehaved, which is enough to suspend it.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[cfg_attr(feature = "test", derive(test_strategy::Arbitrary))]
+pub enum NodeMisbehaviorEvidence {
+    /// Two different responses signed by the node for the same choice of the same request.
+    ConflictingResponses {
+        first: SignedNodeResponse,
+        second: SignedNodeResponse,
+    },
+}
+
+impl NodeMisbehaviorEvidence {
+    /// Checks if the evidence proves that the `node` misbehaved.
+    pub fn proves_misbehavior_of(&self, node: PublicKey) -> bool {
+        match self {
+            NodeMisbehaviorEvidence::ConflictingResponses { first, second } => {
+                first.response.request_id == second.response.request_id
+                    && first.response.choice_index == second.response.choice_index
+                    && first.response != second.response
+                    && first.verify(node)
+                    && second.verify(node)
+            }
+        }
+    }
+
+    /// Returns the [`SuspensionReason`] for a node that misbehaved according to this evidence.
+    pub fn reason(&self) -> SuspensionReason {
+        match self {
+            NodeMisbehaviorEvidence::ConflictingResponses { .. } => {
+                SuspensionReason::ConflictingResponses
+            }
+        }
+    }
+}
+
+/// The suspension of an Atoma node, after which its signatures are no longer accepted.
+#[derive(
+    Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize, async_graphql::SimpleObject,
+)]
+#[cfg_attr(feature = "test", derive(test_strategy::Arbitrary))]
+pub struct NodeSuspension {
+    /// Why the node was suspended.
+    pub reason: SuspensionReason,
+    /// When the node was suspended.
+    pub suspended_at: BlockTime,
+}
+
+/// The reason an Atoma node was suspended.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize, async_graphql::Enum)]
+#[cfg_attr(feature = "test", derive(test_strategy::Arbitrary))]
+pub enum SuspensionReason {
+    /// The node signed two different responses for the same request.
+    ConflictingResponses,
+}
+
+/// The account designated by an Atoma node to receive its rewards, whose hash is signed by the
+/// node to claim them.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct NodeRewardClaim {
+    /// The node whose rewards are claimed.
+    pub node: PublicKey,
+    /// The account that receives the rewards.
+    pub destination: Account,
+}
+
+impl BcsHashable<'_> for NodeRewardClaim {}
+
+/// The policy used to verify that Atoma nodes run in a trusted execution environment.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize, async_graphql::SimpleObject)]
+#[cfg_attr(feature = "test", derive(test_strategy::Arbitrary))]
+pub struct AttestationPolicy {
+    /// The keys trusted to sign attestation reports, such as those of the attestation services
+    /// of the TEE vendors.
+    #[cfg_attr(feature = "test", any(proptest::sample::size_range(0..3).lift()))]
+    pub attesters: Vec<PublicKey>,
+    /// The enclave measurements expected from the attested nodes.
+    #[cfg_attr(feature = "test", any(proptest::sample::size_range(0..3).lift()))]
+    pub measurements: Vec<EnclaveMeasurement>,
+    /// If only receipts signed by nodes with a valid attestation are accepted, in which case
+    /// every logged record must have at least one receipt.
+    pub is_required: bool,
+}
+
+impl AttestationPolicy {
+    /// Checks if an enclave with the `measurement` attested by the `attester` is trusted.
+    pub fn trusts(&self, attester: &PublicKey, measurement: &EnclaveMeasurement) -> bool {
+        self.attesters.contains(attester) && self.measurements.contains(measurement)
+    }
+}
+
+/// The measurement of an enclave running on a TEE platform.
+#[derive(
+    Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize, async_graphql::SimpleObject,
+)]
+#[cfg_attr(feature = "test", derive(test_strategy::Arbitrary))]
+pub struct EnclaveMeasurement {
+    /// The platform running the enclave.
+    pub platform: TeePlatform,
+    /// The hash of the code and configuration loaded in the enclave.
+    pub measurement: CryptoHash,
+}
+
+/// The trusted execution environment platforms that Atoma nodes can run in.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize, async_graphql::Enum)]
+#[cfg_attr(feature = "test", derive(test_strategy::Arbitrary))]
+pub enum TeePlatform {
+    /// Intel Trust Domain Extensions.
+    IntelTdx,
+    /// NVIDIA Confidential Computing.
+    NvidiaCc,
+}
+
+/// An attestation report, signed by an attester to vouch that an Atoma node's key was generated
+/// inside a measured enclave.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[cfg_attr(feature = "test", derive(test_strategy::Arbitrary))]
+pub struct AttestationReport {
+    /// The node whose key is bound to the enclave.
+    pub node: PublicKey,
+    /// The measurement of the enclave.
+    pub enclave: EnclaveMeasurement,
+    /// The key that signed the report.
+    pub attester: PublicKey,
+    /// The attester's signature over the hash of the [`AttestationReport::statement`].
+    pub signature: Signature,
+}
+
+impl AttestationReport {
+    /// Returns the [`AttestationStatement`] signed by the attester.
+    pub fn statement(&self) -> AttestationStatement {
+        AttestationStatement {
+            node: self.node,
+            enclave: self.enclave,
+        }
+    }
+
+    /// Checks if the report was signed by an attester trusted by the `policy` for an enclave
+    /// with one of the expected measurements.
+    pub fn verify(&self, policy: &AttestationPolicy) -> bool {
+        policy.trusts(&self.attester, &self.enclave)
+            && self
+                .attester
+                .verify(CryptoHash::new(&self.statement()), &self.signature)
+    }
+}
+
+/// The binding of an Atoma node's key to a measured enclave, whose hash is signed by an attester.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct AttestationStatement {
+    /// The node whose key is bound to the enclave.
+    pub node: PublicKey,
+    /// The measurement of the enclave.
+    pub enclave: EnclaveMeasurement,
+}
+
+impl BcsHashable<'_> for AttestationStatement {}
+
+/// A verified attestation of an Atoma node.
+#[derive(
+    Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize, async_graphql::SimpleObject,
+)]
+#[cfg_attr(feature = "test", derive(test_strategy::Arbitrary))]
+pub struct NodeAttestation {
+    /// The measurement of the enclave running the node.
+    pub enclave: EnclaveMeasurement,
+    /// The key that signed the attestation report.
+    pub attester: PublicKey,
+    /// When the attestation was registered.
+    pub attested_at: BlockTime,
+}
+
+/// The time a node's key must be active for its reputation to reach half of the score earned
+/// by its verified interactions.
+pub const REPUTATION_UPTIME_SCALE_SECS: u64 = 7 * 24 * 60 * 60;
+
+/// Statistics of the chat interactions produced by an Atoma node, and of its rewards.
+#[derive(
+    Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize, async_graphql::SimpleObject,
+)]
+pub struct NodeStats {
+    /// The number of verified chat interactions produced by the node.
+    pub verified_interactions: u64,
+    /// The number of chat interactions produced by the node that were rejected.
+    pub rejected_interactions: u64,
+    /// The number of reports that proved the node misbehaved.
+    pub reports: u64,
+    /// Since when the node's key is in the set of active Atoma nodes, if it is.
+    pub active_since: Option<Timestamp>,
+    /// The number of tokens served by the node for verified chat interactions.
+    pub tokens_served: u64,
+    /// The rewards accumulated by the node that weren't paid out yet.
+    pub unclaimed_rewards: Amount,
+    /// The rewards already paid out to the node.
+    pub claimed_rewards: Amount,
+}
+
+impl NodeStats {
+    /// Computes the node's reputation score at `now`, between zero and one.
+    ///
+    /// The score is the node's verification success rate, smoothed so that a node without any
+    /// verified or rejected interactions has a rate of one half. It is divided by one more than
+    /// the number of reports against the node, and weighted by the uptime of the node's key,
+    /// reaching half weight after [`REPUTATION_UPTIME_SCALE_SECS`]. Inactive nodes score zero.
+    pub fn reputation(&self, now: Timestamp) -> f64 {
+        let Some(active_since) = self.active_since else {
+            return 0.0;
+        };
+
+        let verified = self.verified_interactions as f64;
+        let rejected = self.rejected_interactions as f64;
+        let success_rate = (verified + 1.0) / (verified + rejected + 2.0);
+        let report_penalty = 1.0 / (1.0 + self.reports as f64);
+
+        let uptime = now.delta_since(active_since).as_micros() as f64;
+        let uptime_scale = TimeDelta::from_secs(REPUTATION_UPTIME_SCALE_SECS).as_micros() as f64;
+        let uptime_weight = uptime / (uptime + uptime_scale);
+
+        success_rate * report_penalty * uptime_weight
+    }
+}
+
+/// A retention policy for the chat log, applied automatically whenever an entry is logged.
+#[derive(
+    Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize, async_graphql::SimpleObject,
+)]
+pub struct ChatLogRetention {
+    /// The maximum age of the entries to keep, based on when they were requested.
+    pub max_age: Option<TimeDelta>,
+    /// The maximum number of entries to keep.
+    pub max_count: Option<u64>,
+    /// Whether to keep a digest of the pruned entries.
+    pub keep_digest: bool,
+}
+
+/// A range of entries pruned from the chat log, summarized by a digest of their hashes.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize, async_graphql::SimpleObject)]
+pub struct PrunedChatLogRange {
+    /// The index of the first pruned entry.
+    pub start: u64,
+    /// The index after the last pruned entry.
+    pub end: u64,
+    /// The hash of the list of hashes of the pruned entries.
+    pub digest: CryptoHash,
+}
+
+impl PrunedChatLogRange {
+    /// Creates a [`PrunedChatLogRange`] for the `entries` pruned from the chat log, starting at
+    /// the `start` index.
+    pub fn new(start: u64, entries: &[ChatLogEntry]) -> Self {
+        let entry_hashes = entries.iter().map(ChatLogEntry::hash).collect();
+
+        PrunedChatLogRange {
+            start,
+            end: start + entries.len() as u64,
+            digest: CryptoHash::new(&PrunedChatLogDigest(entry_hashes)),
+        }
+    }
+}
+
+/// The list of hashes of the entries pruned from the chat log, used to compute their digest.
+#[derive(Deserialize, Serialize)]
+struct PrunedChatLogDigest(Vec<CryptoHash>);
+
+impl BcsHashable<'_> for PrunedChatLogDigest {}
+
+/// The time and the height of a block on a chain.
+#[derive(
+    Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize, async_graphql::SimpleObject,
+)]
+#[cfg_attr(feature = "test", derive(test_strategy::Arbitrary))]
+pub struct BlockTime {
+    /// The timestamp of the block.
+    #[cfg_attr(feature = "test", strategy(any::<u64>().prop_map(Timestamp::from)))]
+    pub timestamp: Timestamp,
+    /// The height of the block.
+    pub block_height: BlockHeight,
+}
+
+/// A function call requested by the AI in response to a prompt.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize, async_graphql::SimpleObject)]
+#[cfg_attr(feature = "test", derive(test_strategy::Arbitrary))]
+pub struct ToolCall {
+    /// The identifier of the call, used to send its result back to the AI.
+    #[cfg_attr(feature = "test", strategy("[A-Za-z0-9_-]*"))]
+    pub id: String,
+    /// The name of the function to call.
+    #[cfg_attr(feature = "test", strategy("[A-Za-z0-9_]*"))]
+    pub name: String,
+    /// The JSON encoded arguments for the function, stored with the same privacy mode as the
+    /// rest of the interaction.
+    pub arguments: ChatContent,
+}
+
+/// The contents of a prompt or a response, as stored on chain.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[cfg_attr(feature = "test", derive(test_strategy::Arbitrary))]
+pub enum ChatContent {
+    /// The plaintext contents, readable by anyone with access to the chain.
+    Public(#[cfg_attr(feature = "test", strategy("[A-Za-z0-9., ]*"))] String),
+
+    /// Only a salted commitment to the plaintext contents.
+    Hashed(CryptoHash),
+
+    /// The contents encrypted to the chain owner, together with a salted commitment to the
+    /// plaintext.
+    Encrypted {
+        ciphertext: Vec<u8>,
+        commitment: CryptoHash,
+    },
+}
+
+impl ChatContent {
+    /// Computes the commitment to a `plaintext` prompt or response.
+    pub fn commit(plaintext: &str) -> CryptoHash {
+        CryptoHash::new(&ChatText(plaintext))
+    }
+
+    /// Computes the commitment to a `plaintext` prompt or response that is not public.
+    ///
+    /// The secret `salt` prevents anyone without it from checking guessed plaintexts against the
+    /// commitment.
+    pub fn commit_salted(plaintext: &str, salt: CryptoHash) -> CryptoHash {
+        CryptoHash::new(&SaltedChatText {
+            salt,
+            text: plaintext,
+        })
+    }
+
+    /// Returns the [`PrivacyMode`] used to store these contents.
+    pub fn privacy(&self) -> PrivacyMode {
+        match self {
+            ChatContent::Public(_) => PrivacyMode::Public,
+            ChatContent::Hashed(_) => PrivacyMode::Hashed,
+            ChatContent::Encrypted { .. } => PrivacyMode::Encrypted,
+        }
+    }
+
+    /// Returns the commitment to the plaintext contents.
+    pub fn commitment(&self) -> CryptoHash {
+        match self {
+            ChatContent::Public(plaintext) => Self::commit(plaintext),
+            ChatContent::Hashed(commitment) => *commitment,
+            ChatContent::Encrypted { commitment, .. } => *commitment,
+        }
+    }
+
+    /// Checks if the `plaintext` matches these contents.
+    ///
+    /// Contents that are not public can only be checked with the `salt` of their commitment.
+    pub fn matches(&self, plaintext: &str, salt: Option<CryptoHash>) -> bool {
+        match self {
+            ChatContent::Public(contents) => contents == plaintext,
+            ChatContent::Hashed(_) | ChatContent::Encrypted { .. } => {
+                salt.is_some_and(|salt| self.commitment() == Self::commit_salted(plaintext, salt))
+            }
+        }
+    }
+}
+
+#[async_graphql::Object]
+impl ChatContent {
+    /// How the contents are stored on chain.
+    #[graphql(name = "privacy")]
+    async fn graphql_privacy(&self) -> PrivacyMode {
+        self.privacy()
+    }
+
+    /// The plaintext contents, if they are public.
+    async fn text(&self) -> Option<&str> {
+        match self {
+            ChatContent::Public(plaintext) => Some(plaintext),
+            ChatContent::Hashed(_) | ChatContent::Encrypted { .. } => None,
+        }
+    }
+
+    /// The commitment to the plaintext contents.
+    #[graphql(name = "commitment")]
+    async fn graphql_commitment(&self) -> CryptoHash {
+        self.commitment()
+    }
+
+    /// The encrypted contents, if they are encrypted.
+    async fn ciphertext(&self) -> Option<&[u8]> {
+        match self {
+            ChatContent::Encrypted { ciphertext, .. } => Some(ciphertext),
+            ChatContent::Public(_) | ChatContent::Hashed(_) => None,
+        }
+    }
+}
+
+/// How the contents of a [`ChatInteraction`] are stored on chain.
+#[derive(
+    Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize, async_graphql::Enum,
+)]
+#[cfg_attr(feature = "test", derive(test_strategy::Arbitrary))]
+pub enum PrivacyMode {
+    /// The plaintext is stored on chain.
+    #[default]
+    Public,
+    /// Only a commitment to the plaintext is stored on chain.
+    Hashed,
+    /// The plaintext is encrypted to the chain owner, and stored together with a commitment.
+    Encrypted,
+}
+
+/// An embedding vector computed for some input text.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize, async_graphql::SimpleObject)]
+#[cfg_attr(feature = "test", derive(test_strategy::Arbitrary))]
+pub struct EmbeddingRecord {
+    /// The commitment to the input text, as computed by [`ChatContent::commit`].
+    pub input_hash: CryptoHash,
+    #[cfg_attr(feature = "test", strategy("[A-Za-z0-9./-]*"))]
+    pub model: String,
+    #[cfg_attr(feature = "test", strategy(proptest::strategy::Just(#vector.len() as u32)))]
+    pub dimensions: u32,
+    #[cfg_attr(
+        feature = "test",
+        strategy(proptest::collection::vec(-1.0_f32..1.0, 0..32))
+    )]
+    #[serde(with = "float_bits")]
+    pub vector: Vec<f32>,
+}
+
+impl EmbeddingRecord {
+    /// Returns the ID of this embedding, which is the hash of its contents.
+    pub fn id(&self) -> CryptoHash {
+        CryptoHash::new(self)
+    }
+}
+
+impl BcsHashable<'_> for EmbeddingRecord {}
+
+/// A set of images generated from a prompt.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize, async_graphql::SimpleObject)]
+#[cfg_attr(feature = "test", derive(test_strategy::Arbitrary))]
+pub struct ImageGenerationRecord {
     #[cfg_attr(feature = "test", strategy("[A-Za-z0-9., ]*"))]
     pub prompt: String,
-    #[cfg_attr(feature = "test", strategy("[A-Za-z0-9., ]*"))]
-    pub response: String,
+    #[cfg_attr(feature = "test", strategy("[A-Za-z0-9./-]*"))]
+    pub model: String,
+    #[cfg_attr(feature = "test", any(proptest::sample::size_range(0..5).lift()))]
+    pub images: Vec<ImageRecord>,
+}
+
+impl ImageGenerationRecord {
+    /// Returns the ID of this image generation, which is the hash of its contents.
+    pub fn id(&self) -> CryptoHash {
+        CryptoHash::new(self)
+    }
+}
+
+impl BcsHashable<'_> for ImageGenerationRecord {}
+
+/// A single generated image.
+///
+/// The image itself is kept off-chain, and can be published as a data blob whose hash is the
+/// image's `content_hash`.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize, async_graphql::SimpleObject)]
+#[cfg_attr(feature = "test", derive(test_strategy::Arbitrary))]
+pub struct ImageRecord {
+    #[cfg_attr(
+        feature = "test",
+        strategy(any::<CryptoHash>().prop_map(DataBlobHash))
+    )]
+    pub content_hash: DataBlobHash,
+    #[cfg_attr(
+        feature = "test",
+        strategy(proptest::option::of("https://[a-z.]+/[a-z0-9]*"))
+    )]
+    pub url: Option<String>,
 }
 
-/// Representation of an Atoma node's public key.
+/// Serialization of floating point vectors as the raw bits of their elements, because BCS does
+/// not support floating point numbers.
+mod float_bits {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S>(vector: &[f32], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_seq(vector.iter().map(|element| element.to_bits()))
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<f32>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let bits = Vec::<u32>::deserialize(deserializer)?;
+
+        Ok(bits.into_iter().map(f32::from_bits).collect())
+    }
+}
+
+/// Serialization of compressed elliptic curve points as fixed size arrays, because `serde` only
+/// supports arrays of up to 32 elements.
+mod compressed_point {
+    use std::fmt;
+
+    use serde::{
+        de::{Error, SeqAccess, Visitor},
+        ser::SerializeTuple,
+        Deserializer, Serializer,
+    };
+
+    /// The size of a compressed SEC1 encoded point.
+    const SIZE: usize = 33;
+
+    pub fn serialize<S>(point: &[u8; SIZE], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut tuple = serializer.serialize_tuple(SIZE)?;
+
+        for byte in point {
+            tuple.serialize_element(byte)?;
+        }
+
+        tuple.end()
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<[u8; SIZE], D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_tuple(SIZE, CompressedPointVisitor)
+    }
+
+    /// A [`Visitor`] that reads the bytes of a compressed point.
+    struct CompressedPointVisitor;
+
+    impl<'de> Visitor<'de> for CompressedPointVisitor {
+        type Value = [u8; SIZE];
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            write!(formatter, "an array of {SIZE} bytes")
+        }
+
+        fn visit_seq<A>(self, mut sequence: A) -> Result<Self::Value, A::Error>
+        where
+            A: SeqAccess<'de>,
+        {
+            let mut point = [0; SIZE];
+
+            for (index, byte) in point.iter_mut().enumerate() {
+                *byte = sequence
+                    .next_element()?
+                    .ok_or_else(|| A::Error::invalid_length(index, &self))?;
+            }
+
+            Ok(point)
+        }
+    }
+}
+
+/// The plaintext of a prompt or response, used to compute its commitment.
+#[derive(Deserialize, Serialize)]
+struct ChatText<'text>(&'text str);
+
+impl<'text> BcsHashable<'text> for ChatText<'text> {}
+
+/// The plaintext of a prompt or response that is not public, together with the secret salt of
+/// its commitment.
+#[derive(Deserialize, Serialize)]
+struct SaltedChatText<'text> {
+    salt: CryptoHash,
+    text: &'text str,
+}
+
+impl<'text> BcsHashable<'text> for SaltedChatText<'text> {}
+
+/// Representation of an Atoma node's public key, tagged with its signature scheme.
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Deserialize, Serialize)]
 #[cfg_attr(feature = "test", derive(test_strategy::Arbitrary))]
-pub struct PublicKey([u8; 32]);
+pub enum PublicKey {
+    /// An Ed25519 public key.
+    Ed25519([u8; 32]),
+    /// A compressed SEC1 encoded ECDSA public key on the secp256k1 curve.
+    Secp256k1(
+        #[serde(with = "compressed_point")]
+        #[cfg_attr(feature = "test", strategy(proptest::array::uniform(any::<u8>())))]
+        [u8; 33],
+    ),
+    /// A compressed SEC1 encoded ECDSA public key on the secp256r1 (P-256) curve.
+    Secp256r1(
+        #[serde(with = "compressed_point")]
+        #[cfg_attr(feature = "test", strategy(proptest::array::uniform(any::<u8>())))]
+        [u8; 33],
+    ),
+}
 async_graphql::scalar!(PublicKey);
 
+impl PublicKey {
+    /// Returns the [`SignatureScheme`] of this key.
+    pub fn scheme(&self) -> SignatureScheme {
+        match self {
+            PublicKey::Ed25519(_) => SignatureScheme::Ed25519,
+            PublicKey::Secp256k1(_) => SignatureScheme::Secp256k1,
+            PublicKey::Secp256r1(_) => SignatureScheme::Secp256r1,
+        }
+    }
+
+    /// Checks if `signature` is a valid signature by this key over the bytes of the `hash`,
+    /// according to the key's [`SignatureScheme`].
+    ///
+    /// ECDSA signatures are expected in their fixed size `r || s` encoding, and are computed
+    /// over the SHA-256 digest of the bytes.
+    pub fn verify(&self, hash: CryptoHash, signature: &Signature) -> bool {
+        let message = hash.as_bytes().as_slice();
+
+        match self {
+            PublicKey::Ed25519(bytes) => {
+                let Ok(verifying_key) = ed25519_dalek::VerifyingKey::from_bytes(bytes) else {
+                    return false;
+                };
+                let Ok(signature) = ed25519_dalek::Signature::from_slice(&signature.0) else {
+                    return false;
+                };
+
+                verifying_key.verify_strict(message, &signature).is_ok()
+            }
+            PublicKey::Secp256k1(bytes) => {
+                let Ok(verifying_key) = k256::ecdsa::VerifyingKey::from_sec1_bytes(bytes) else {
+                    return false;
+                };
+                let Ok(signature) = k256::ecdsa::Signature::from_slice(&signature.0) else {
+                    return false;
+                };
+
+                verifying_key.verify(message, &signature).is_ok()
+            }
+            PublicKey::Secp256r1(bytes) => {
+                let Ok(verifying_key) = p256::ecdsa::VerifyingKey::from_sec1_bytes(bytes) else {
+                    return false;
+                };
+                let Ok(signature) = p256::ecdsa::Signature::from_slice(&signature.0) else {
+                    return false;
+                };
+
+                verifying_key.verify(message, &signature).is_ok()
+            }
+        }
+    }
+}
+
 impl From<[u8; 32]> for PublicKey {
     fn from(bytes: [u8; 32]) -> Self {
-        PublicKey(bytes)
+        PublicKey::Ed25519(bytes)
+    }
+}
+
+/// The signature schemes supported for Atoma node keys.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize, async_graphql::Enum)]
+#[cfg_attr(feature = "test", derive(test_strategy::Arbitrary))]
+pub enum SignatureScheme {
+    Ed25519,
+    Secp256k1,
+    Secp256r1,
+}
+
+/// Representation of a signature by an Atoma node.
+#[derive(Clone, Debug, Eq, Hash, PartialEq, Deserialize, Serialize)]
+#[cfg_attr(feature = "test", derive(test_strategy::Arbitrary))]
+pub struct Signature(Vec<u8>);
+async_graphql::scalar!(Signature);
+
+impl From<[u8; 64]> for Signature {
+    fn from(bytes: [u8; 64]) -> Self {
+        Signature(bytes.to_vec())
     }
 }
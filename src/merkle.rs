@@ -0,0 +1,205 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! A Merkle mountain range over the chat log.
+//!
+//! The mountain range is a list of perfect binary Merkle trees, called peaks, with strictly
+//! decreasing heights. Appending a leaf only merges the peaks to its left, so the range can be
+//! maintained as the log grows, and an inclusion proof for an entry only needs the path to its
+//! peak and the hashes of the peaks.
+
+use linera_sdk::linera_base_types::{BcsHashable, CryptoHash};
+use serde::{Deserialize, Serialize};
+
+use crate::LoggedChatInteraction;
+
+/// The peaks of a Merkle mountain range over a log of entries.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct MerkleMountainRange {
+    length: u64,
+    peaks: Vec<CryptoHash>,
+}
+
+impl MerkleMountainRange {
+    /// Returns the number of entries in the range.
+    pub fn len(&self) -> u64 {
+        self.length
+    }
+
+    /// Returns `true` if there are no entries in the range.
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+
+    /// Returns the hashes of the peaks, from the highest to the lowest.
+    pub fn peaks(&self) -> &[CryptoHash] {
+        &self.peaks
+    }
+
+    /// Returns the Merkle root, which commits to all entries in the range.
+    pub fn root(&self) -> CryptoHash {
+        root_hash(&self.peaks)
+    }
+
+    /// Appends the entry with `entry_hash` to the range, returning the new nodes that were
+    /// created.
+    pub fn push(&mut self, entry_hash: CryptoHash) -> Vec<(NodePosition, CryptoHash)> {
+        let mut position = NodePosition {
+            height: 0,
+            index: self.length,
+        };
+        let mut hash = leaf_hash(entry_hash);
+        let mut new_nodes = vec![(position, hash)];
+
+        while position.index % 2 == 1 {
+            let left_sibling = self
+                .peaks
+                .pop()
+                .expect("Left sibling of a right child should be a peak");
+
+            hash = branch_hash(left_sibling, hash);
+            position = position.parent();
+            new_nodes.push((position, hash));
+        }
+
+        self.peaks.push(hash);
+        self.length += 1;
+
+        new_nodes
+    }
+
+    /// Returns the positions of the peaks, from the highest to the lowest.
+    pub fn peak_positions(&self) -> Vec<NodePosition> {
+        peak_positions(self.length)
+    }
+}
+
+/// The position of a node in a Merkle mountain range.
+///
+/// Leaves have height zero, and the node at `index` covers the leaves from `index * 2^height`
+/// (inclusive) to `(index + 1) * 2^height` (exclusive).
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct NodePosition {
+    pub height: u8,
+    pub index: u64,
+}
+
+impl NodePosition {
+    /// Returns the position of the node's parent.
+    pub fn parent(self) -> Self {
+        NodePosition {
+            height: self.height + 1,
+            index: self.index / 2,
+        }
+    }
+
+    /// Returns the position of the node's sibling.
+    pub fn sibling(self) -> Self {
+        NodePosition {
+            height: self.height,
+            index: self.index ^ 1,
+        }
+    }
+
+    /// Checks if the node covers the leaf at `leaf_index`.
+    pub fn covers(self, leaf_index: u64) -> bool {
+        leaf_index >> self.height == self.index
+    }
+}
+
+/// A proof that an entry is included in the chat log with a specific Merkle root.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize, async_graphql::SimpleObject)]
+pub struct ChatLogInclusionProof {
+    /// The index of the entry in the chat log.
+    pub index: u64,
+    /// The length of the chat log when the proof was produced.
+    pub log_length: u64,
+    /// The hashes of the siblings in the path from the entry to its peak, from the bottom up.
+    pub siblings: Vec<CryptoHash>,
+    /// The hashes of the peaks, from the highest to the lowest.
+    pub peaks: Vec<CryptoHash>,
+}
+
+impl ChatLogInclusionProof {
+    /// Checks if the proof shows that the `entry` is included in the chat log with `root`.
+    pub fn verify(&self, entry: &LoggedChatInteraction, root: CryptoHash) -> bool {
+        self.verify_hash(CryptoHash::new(entry), root)
+    }
+
+    /// Checks if the proof shows that an entry with `entry_hash` is included in the chat log
+    /// with `root`.
+    ///
+    /// This allows proving the inclusion of entries that were later redacted.
+    pub fn verify_hash(&self, entry_hash: CryptoHash, root: CryptoHash) -> bool {
+        let positions = peak_positions(self.log_length);
+        let Some(peak_index) = positions
+            .iter()
+            .position(|position| position.covers(self.index))
+        else {
+            return false;
+        };
+
+        if positions.len() != self.peaks.len()
+            || usize::from(positions[peak_index].height) != self.siblings.len()
+        {
+            return false;
+        }
+
+        let mut hash = leaf_hash(entry_hash);
+        for (height, sibling) in self.siblings.iter().enumerate() {
+            hash = if (self.index >> height) % 2 == 1 {
+                branch_hash(*sibling, hash)
+            } else {
+                branch_hash(hash, *sibling)
+            };
+        }
+
+        hash == self.peaks[peak_index] && root_hash(&self.peaks) == root
+    }
+}
+
+/// A node in a Merkle mountain range, used to compute its hash.
+#[derive(Deserialize, Serialize)]
+enum MerkleNode {
+    Leaf(CryptoHash),
+    Branch(CryptoHash, CryptoHash),
+    Root(Vec<CryptoHash>),
+}
+
+impl BcsHashable<'_> for MerkleNode {}
+
+/// Returns the hash of the leaf for the entry with `entry_hash`.
+fn leaf_hash(entry_hash: CryptoHash) -> CryptoHash {
+    CryptoHash::new(&MerkleNode::Leaf(entry_hash))
+}
+
+/// Returns the hash of the branch with the `left` and `right` children.
+fn branch_hash(left: CryptoHash, right: CryptoHash) -> CryptoHash {
+    CryptoHash::new(&MerkleNode::Branch(left, right))
+}
+
+/// Returns the root hash of a Merkle mountain range with the `peaks`.
+fn root_hash(peaks: &[CryptoHash]) -> CryptoHash {
+    CryptoHash::new(&MerkleNode::Root(peaks.to_vec()))
+}
+
+/// Returns the positions of the peaks of a Merkle mountain range with `length` leaves, from the
+/// highest to the lowest.
+fn peak_positions(length: u64) -> Vec<NodePosition> {
+    let mut positions = Vec::new();
+    let mut first_leaf = 0;
+
+    for height in (0..u64::BITS as u8).rev() {
+        let size = 1 << height;
+
+        if length & size != 0 {
+            positions.push(NodePosition {
+                height,
+                index: first_leaf >> height,
+            });
+            first_leaf += size;
+        }
+    }
+
+    positions
+}
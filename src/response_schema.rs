@@ -0,0 +1,278 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Validation of structured chat completion responses.
+//!
+//! Only the subset of JSON Schema that is supported by structured outputs in chat completion
+//! APIs is implemented. Annotation keywords such as `title` and `description` are ignored, and
+//! schemas with other keywords are rejected.
+
+use serde_json::{Map, Value};
+
+/// A JSON Schema that structured responses must follow.
+#[derive(Clone, Debug)]
+pub struct ResponseSchema(Value);
+
+impl ResponseSchema {
+    /// Creates a [`ResponseSchema`] after checking that `schema` only uses supported keywords.
+    pub fn new(schema: Value) -> Result<Self, String> {
+        check_schema(&schema, "#")?;
+        Ok(ResponseSchema(schema))
+    }
+
+    /// Returns the JSON representation of the schema.
+    pub fn as_json(&self) -> &Value {
+        &self.0
+    }
+
+    /// Returns a description of each location where the `document` does not follow the schema.
+    pub fn violations(&self, document: &Value) -> Vec<String> {
+        let mut violations = Vec::new();
+        collect_violations(&self.0, document, "#", &mut violations);
+        violations
+    }
+}
+
+/// Keywords that only document a schema and do not affect validation.
+const ANNOTATION_KEYWORDS: &[&str] = &["$schema", "$id", "title", "description", "default"];
+
+/// The names that may be used in a `type` keyword.
+const TYPE_NAMES: &[&str] = &[
+    "null", "boolean", "object", "array", "number", "integer", "string",
+];
+
+/// Checks that the `schema` at `path` is well formed and only uses supported keywords.
+fn check_schema(schema: &Value, path: &str) -> Result<(), String> {
+    let keywords = match schema {
+        Value::Bool(_) => return Ok(()),
+        Value::Object(keywords) => keywords,
+        _ => return Err(format!("{path}: a schema must be an object or a boolean")),
+    };
+
+    for (keyword, value) in keywords {
+        let location = format!("{path}/{keyword}");
+        let is_valid = match keyword.as_str() {
+            "type" => match value {
+                Value::String(name) => TYPE_NAMES.contains(&name.as_str()),
+                Value::Array(names) => names
+                    .iter()
+                    .all(|name| name.as_str().is_some_and(|name| TYPE_NAMES.contains(&name))),
+                _ => false,
+            },
+            "enum" => value.is_array(),
+            "const" => true,
+            "required" => value
+                .as_array()
+                .is_some_and(|names| names.iter().all(Value::is_string)),
+            "properties" => {
+                let Some(properties) = value.as_object() else {
+                    return Err(format!("{location}: expected an object"));
+                };
+                for (name, property) in properties {
+                    check_schema(property, &format!("{location}/{name}"))?;
+                }
+                true
+            }
+            "additionalProperties" | "items" => {
+                check_schema(value, &location)?;
+                true
+            }
+            "anyOf" => {
+                let Some(alternatives) = value.as_array().filter(|list| !list.is_empty()) else {
+                    return Err(format!("{location}: expected a non-empty array"));
+                };
+                for (index, alternative) in alternatives.iter().enumerate() {
+                    check_schema(alternative, &format!("{location}/{index}"))?;
+                }
+                true
+            }
+            "minimum" | "maximum" => value.is_number(),
+            "minLength" | "maxLength" | "minItems" | "maxItems" => value.is_u64(),
+            keyword if ANNOTATION_KEYWORDS.contains(&keyword) => true,
+            _ => return Err(format!("{location}: unsupported keyword")),
+        };
+
+        if !is_valid {
+            return Err(format!("{location}: invalid value {value}"));
+        }
+    }
+
+    Ok(())
+}
+
+/// Adds a description of each location where the `instance` at `path` does not follow the
+/// `schema` to the list of `violations`.
+fn collect_violations(schema: &Value, instance: &Value, path: &str, violations: &mut Vec<String>) {
+    let keywords = match schema {
+        Value::Bool(true) => return,
+        Value::Bool(false) => {
+            violations.push(format!("{path}: no value is allowed"));
+            return;
+        }
+        Value::Object(keywords) => keywords,
+        _ => unreachable!("Schema should have been checked when it was created"),
+    };
+
+    if let Some(types) = keywords.get("type") {
+        let matches_type = match types {
+            Value::Array(names) => names.iter().any(|name| has_type(instance, name)),
+            name => has_type(instance, name),
+        };
+        if !matches_type {
+            violations.push(format!("{path}: {instance} is not of type {types}"));
+            return;
+        }
+    }
+
+    if let Some(Value::Array(allowed_values)) = keywords.get("enum") {
+        if !allowed_values.contains(instance) {
+            violations.push(format!(
+                "{path}: {instance} is not one of {}",
+                keywords["enum"]
+            ));
+        }
+    }
+
+    if let Some(expected) = keywords.get("const") {
+        if instance != expected {
+            violations.push(format!("{path}: {instance} is not {expected}"));
+        }
+    }
+
+    if let Some(alternatives) = keywords.get("anyOf").and_then(Value::as_array) {
+        let matches_any = alternatives.iter().any(|alternative| {
+            let mut alternative_violations = Vec::new();
+            collect_violations(alternative, instance, path, &mut alternative_violations);
+            alternative_violations.is_empty()
+        });
+        if !matches_any {
+            violations.push(format!(
+                "{path}: {instance} does not match any of the alternatives"
+            ));
+        }
+    }
+
+    match instance {
+        Value::Object(fields) => collect_object_violations(keywords, fields, path, violations),
+        Value::Array(elements) => {
+            check_bounds(
+                keywords,
+                "minItems",
+                "maxItems",
+                elements.len(),
+                path,
+                violations,
+            );
+
+            if let Some(item_schema) = keywords.get("items") {
+                for (index, element) in elements.iter().enumerate() {
+                    collect_violations(
+                        item_schema,
+                        element,
+                        &format!("{path}/{index}"),
+                        violations,
+                    );
+                }
+            }
+        }
+        Value::String(text) => {
+            let length = text.chars().count();
+            check_bounds(keywords, "minLength", "maxLength", length, path, violations);
+        }
+        Value::Number(number) => {
+            let number = number
+                .as_f64()
+                .expect("JSON numbers should be representable as `f64`");
+            if let Some(minimum) = keywords.get("minimum").and_then(Value::as_f64) {
+                if number < minimum {
+                    violations.push(format!("{path}: {number} is less than {minimum}"));
+                }
+            }
+            if let Some(maximum) = keywords.get("maximum").and_then(Value::as_f64) {
+                if number > maximum {
+                    violations.push(format!("{path}: {number} is greater than {maximum}"));
+                }
+            }
+        }
+        Value::Null | Value::Bool(_) => {}
+    }
+}
+
+/// Adds the violations of the object keywords in `keywords` by the object `fields` at `path` to
+/// the list of `violations`.
+fn collect_object_violations(
+    keywords: &Map<String, Value>,
+    fields: &Map<String, Value>,
+    path: &str,
+    violations: &mut Vec<String>,
+) {
+    for name in keywords
+        .get("required")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+    {
+        if !fields.contains_key(name) {
+            violations.push(format!("{path}: missing required property {name:?}"));
+        }
+    }
+
+    let properties = keywords.get("properties").and_then(Value::as_object);
+
+    for (name, field) in fields {
+        let field_path = format!("{path}/{name}");
+        match properties.and_then(|properties| properties.get(name)) {
+            Some(property_schema) => {
+                collect_violations(property_schema, field, &field_path, violations)
+            }
+            None => {
+                if let Some(additional_schema) = keywords.get("additionalProperties") {
+                    collect_violations(additional_schema, field, &field_path, violations);
+                }
+            }
+        }
+    }
+}
+
+/// Adds a violation to the list of `violations` if the `length` of the instance at `path` is
+/// outside the bounds specified by the `minimum` and `maximum` keywords.
+fn check_bounds(
+    keywords: &Map<String, Value>,
+    minimum: &str,
+    maximum: &str,
+    length: usize,
+    path: &str,
+    violations: &mut Vec<String>,
+) {
+    let length = length as u64;
+
+    if let Some(minimum) = keywords.get(minimum).and_then(Value::as_u64) {
+        if length < minimum {
+            violations.push(format!("{path}: length {length} is less than {minimum}"));
+        }
+    }
+    if let Some(maximum) = keywords.get(maximum).and_then(Value::as_u64) {
+        if length > maximum {
+            violations.push(format!("{path}: length {length} is greater than {maximum}"));
+        }
+    }
+}
+
+/// Checks if the `instance` has the type with the provided `name`.
+fn has_type(instance: &Value, name: &Value) -> bool {
+    match (name.as_str(), instance) {
+        (Some("null"), Value::Null)
+        | (Some("boolean"), Value::Bool(_))
+        | (Some("object"), Value::Object(_))
+        | (Some("array"), Value::Array(_))
+        | (Some("number"), Value::Number(_))
+        | (Some("string"), Value::String(_)) => true,
+        (Some("integer"), Value::Number(number)) => {
+            number.is_i64()
+                || number.is_u64()
+                || number.as_f64().is_some_and(|float| float.fract() == 0.0)
+        }
+        _ => false,
+    }
+}
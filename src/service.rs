@@ -3,21 +3,34 @@
 
 #![cfg_attr(target_arch = "wasm32", no_main)]
 
+mod response_schema;
 mod state;
 #[cfg(test)]
 #[path = "./service_unit_tests.rs"]
 mod tests;
 
-use std::sync::Arc;
+use std::{collections::BTreeSet, fmt, sync::Arc};
 
-use async_graphql::{EmptySubscription, Schema};
-use atoma_demo::{ChatInteraction, Operation};
+use async_graphql::{EmptySubscription, ErrorExtensions, Schema};
+use atoma_demo::{
+    merkle::{ChatLogInclusionProof, NodePosition},
+    ChatContent, ChatInteraction, ChatLogEntry, EmbeddingRecord, ImageGenerationRecord,
+    ImageRecord, LoggedChatInteraction, NodeReceipt, NodeStats, Operation, PrivacyMode, PublicKey,
+    ToolCall,
+};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
 use linera_sdk::{
-    bcs, ensure, http, linera_base_types::WithServiceAbi, views::View, Service, ServiceRuntime,
+    bcs, ensure, http,
+    linera_base_types::{
+        AccountOwner, Amount, BcsHashable, Blob, CryptoHash, Timestamp, WithServiceAbi,
+    },
+    views::View,
+    DataBlobHash, Service, ServiceRuntime,
 };
-use serde::{Deserialize, Serialize};
+use rand_chacha::{rand_core::SeedableRng, ChaCha20Rng};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
-use self::state::Application;
+use self::{response_schema::ResponseSchema, state::Application};
 
 #[derive(Clone)]
 pub struct ApplicationService {
@@ -47,9 +60,23 @@ impl Service for ApplicationService {
 
     async fn handle_query(&self, query: Self::Query) -> Self::QueryResponse {
         Schema::build(
-            self.state.clone(),
+            Query(
+                self.state.clone(),
+                ChatLogQuery {
+                    state: self.state.clone(),
+                },
+                NodeQuery {
+                    runtime: self.runtime.clone(),
+                    state: self.state.clone(),
+                },
+                AtomaQuery {
+                    runtime: self.runtime.clone(),
+                    state: self.state.clone(),
+                },
+            ),
             Mutation {
                 runtime: self.runtime.clone(),
+                state: self.state.clone(),
             },
             EmptySubscription,
         )
@@ -59,43 +86,900 @@ impl Service for ApplicationService {
     }
 }
 
+/// Root type that defines all the GraphQL queries available from the service.
+#[derive(async_graphql::MergedObject)]
+pub struct Query(Arc<Application>, ChatLogQuery, NodeQuery, AtomaQuery);
+
+/// GraphQL queries to inspect the chat log beyond reading its raw entries.
+pub struct ChatLogQuery {
+    state: Arc<Application>,
+}
+
+#[async_graphql::Object]
+impl ChatLogQuery {
+    /// Verifies if the plaintext `prompt` and `response` match the logged chat interaction at
+    /// `index`.
+    ///
+    /// Contents that are not public are only verified with the `prompt_salt` and `response_salt`
+    /// returned when the interaction was produced.
+    async fn verify_chat_interaction(
+        &self,
+        index: u64,
+        prompt: String,
+        response: String,
+        prompt_salt: Option<CryptoHash>,
+        response_salt: Option<CryptoHash>,
+    ) -> async_graphql::Result<bool> {
+        let pruned_count = *self.state.chat_log_pruned_count.get();
+        ensure!(
+            index >= pruned_count,
+            async_graphql::Error::new(format!("Chat interaction at {index} was pruned"))
+        );
+
+        let entry =
+            self.state.chat_log.get(&index).await?.ok_or_else(|| {
+                async_graphql::Error::new(format!("No chat interaction at {index}"))
+            })?;
+
+        let ChatLogEntry::Logged(LoggedChatInteraction { interaction, .. }) = entry else {
+            return Err(async_graphql::Error::new(format!(
+                "Chat interaction at {index} was redacted"
+            )));
+        };
+
+        Ok(interaction.prompt.matches(&prompt, prompt_salt)
+            && interaction.response.matches(&response, response_salt))
+    }
+
+    /// Returns the chat log's entries that were not pruned.
+    async fn chat_log(&self) -> ChatLog {
+        ChatLog {
+            state: self.state.clone(),
+        }
+    }
+
+    /// Returns the Merkle root of the chat log, which commits to all of its entries.
+    async fn chat_log_merkle_root(&self) -> CryptoHash {
+        self.state.chat_log_merkle_range.get().root()
+    }
+
+    /// Returns a proof that the logged chat interaction at `index` is included in the chat log
+    /// with the current Merkle root.
+    async fn chat_log_inclusion_proof(
+        &self,
+        index: u64,
+    ) -> async_graphql::Result<ChatLogInclusionProof> {
+        let merkle_range = self.state.chat_log_merkle_range.get();
+        let peak_positions = merkle_range.peak_positions();
+        let peak = peak_positions
+            .iter()
+            .find(|position| position.covers(index))
+            .ok_or_else(|| async_graphql::Error::new(format!("No chat interaction at {index}")))?;
+
+        let mut siblings = Vec::with_capacity(peak.height.into());
+        let mut position = NodePosition { height: 0, index };
+
+        while position != *peak {
+            let sibling = self
+                .state
+                .chat_log_merkle_nodes
+                .get(&position.sibling())
+                .await?
+                .ok_or_else(|| async_graphql::Error::new("Missing Merkle node in chat log"))?;
+
+            siblings.push(sibling);
+            position = position.parent();
+        }
+
+        Ok(ChatLogInclusionProof {
+            index,
+            log_length: merkle_range.len(),
+            siblings,
+            peaks: merkle_range.peaks().to_vec(),
+        })
+    }
+
+    /// Lists the logged chat interactions requested from `since` (inclusive) until `until`
+    /// (exclusive).
+    ///
+    /// Redacted interactions are not listed.
+    async fn chat_interactions(
+        &self,
+        since: Option<Timestamp>,
+        until: Option<Timestamp>,
+    ) -> async_graphql::Result<Vec<LoggedChatInteraction>> {
+        Ok(self
+            .unredacted_chat_interactions()
+            .await?
+            .filter(|entry| was_requested_between(entry, since, until))
+            .collect())
+    }
+
+    /// Lists the logged chat interactions requested by the `requester`, optionally restricted to
+    /// the ones requested from `since` (inclusive) until `until` (exclusive).
+    ///
+    /// Redacted interactions are not listed.
+    async fn chat_interactions_by_requester(
+        &self,
+        requester: AccountOwner,
+        since: Option<Timestamp>,
+        until: Option<Timestamp>,
+    ) -> async_graphql::Result<Vec<LoggedChatInteraction>> {
+        Ok(self
+            .unredacted_chat_interactions()
+            .await?
+            .filter(|entry| entry.requester == Some(requester))
+            .filter(|entry| was_requested_between(entry, since, until))
+            .collect())
+    }
+}
+
+impl ChatLogQuery {
+    /// Returns the chat log's entries that were not redacted.
+    async fn unredacted_chat_interactions(
+        &self,
+    ) -> async_graphql::Result<impl Iterator<Item = LoggedChatInteraction>> {
+        let chat_log = ChatLog {
+            state: self.state.clone(),
+        };
+        let entries = chat_log.read_entries(None, None).await?;
+
+        Ok(entries.into_iter().filter_map(|entry| match entry {
+            ChatLogEntry::Logged(logged_interaction) => Some(logged_interaction),
+            ChatLogEntry::Redacted(_) => None,
+        }))
+    }
+}
+
+/// The entries of the chat log that were not pruned, indexed by their position in the log.
+pub struct ChatLog {
+    state: Arc<Application>,
+}
+
+#[async_graphql::Object]
+impl ChatLog {
+    /// Returns the number of entries that were not pruned.
+    async fn count(&self) -> u64 {
+        self.end_index() - *self.state.chat_log_pruned_count.get()
+    }
+
+    /// Returns the entry at `index`, if it was logged and not pruned.
+    async fn entry(&self, index: u64) -> async_graphql::Result<Option<ChatLogEntry>> {
+        Ok(self.state.chat_log.get(&index).await?)
+    }
+
+    /// Returns the entries from the `start` index (inclusive) until the `end` index (exclusive),
+    /// which are the indices the entries were logged at.
+    ///
+    /// The range is clamped to the entries that were not pruned, and includes all of them by
+    /// default.
+    async fn entries(
+        &self,
+        start: Option<u64>,
+        end: Option<u64>,
+    ) -> async_graphql::Result<Vec<ChatLogEntry>> {
+        self.read_entries(start, end).await
+    }
+}
+
+impl ChatLog {
+    /// Returns the index after the last entry of the chat log.
+    fn end_index(&self) -> u64 {
+        self.state.chat_log_merkle_range.get().len()
+    }
+
+    /// Reads the entries from the `start` index (inclusive) until the `end` index (exclusive),
+    /// clamped to the entries that were not pruned.
+    async fn read_entries(
+        &self,
+        start: Option<u64>,
+        end: Option<u64>,
+    ) -> async_graphql::Result<Vec<ChatLogEntry>> {
+        let pruned_count = *self.state.chat_log_pruned_count.get();
+        let end_index = self.end_index();
+        let start = start.map_or(pruned_count, |start| start.max(pruned_count));
+        let end = end.map_or(end_index, |end| end.min(end_index));
+        let mut entries = Vec::with_capacity(end.saturating_sub(start) as usize);
+
+        for index in start..end {
+            let entry = self.state.chat_log.get(&index).await?.ok_or_else(|| {
+                async_graphql::Error::new(format!("Missing chat log entry at {index}"))
+            })?;
+
+            entries.push(entry);
+        }
+
+        Ok(entries)
+    }
+}
+
+/// Checks if the `entry` was requested to be logged from `since` (inclusive) until `until`
+/// (exclusive).
+fn was_requested_between(
+    entry: &LoggedChatInteraction,
+    since: Option<Timestamp>,
+    until: Option<Timestamp>,
+) -> bool {
+    let requested_at = entry.requested_at.timestamp;
+
+    since.is_none_or(|since| requested_at >= since)
+        && until.is_none_or(|until| requested_at < until)
+}
+
+/// GraphQL queries to compare the Atoma nodes.
+pub struct NodeQuery {
+    runtime: Arc<ServiceRuntime<ApplicationService>>,
+    state: Arc<Application>,
+}
+
+#[async_graphql::Object]
+impl NodeQuery {
+    /// Returns the active Atoma nodes that aren't suspended, sorted from the highest to the
+    /// lowest reputation.
+    async fn node_rankings(&self) -> async_graphql::Result<Vec<NodeRanking>> {
+        let now = self.runtime.system_time();
+        let mut rankings = Vec::new();
+
+        for node in self.state.active_atoma_nodes.indices().await? {
+            if self.state.suspended_atoma_nodes.contains_key(&node).await? {
+                continue;
+            }
+
+            let stats = self.state.node_stats.get(&node).await?.unwrap_or_default();
+
+            rankings.push(NodeRanking {
+                node,
+                reputation: stats.reputation(now),
+                stats,
+            });
+        }
+
+        rankings.sort_by(|left, right| {
+            right
+                .reputation
+                .total_cmp(&left.reputation)
+                .then(left.node.cmp(&right.node))
+        });
+
+        Ok(rankings)
+    }
+}
+
+/// An Atoma node in the rankings, with its reputation.
+#[derive(Clone, Debug, async_graphql::SimpleObject)]
+pub struct NodeRanking {
+    /// The node's public key.
+    node: PublicKey,
+    /// The node's reputation score, between zero and one.
+    reputation: f64,
+    /// The statistics the reputation was computed from.
+    stats: NodeStats,
+}
+
+/// GraphQL queries that forward requests to the Atoma network.
+pub struct AtomaQuery {
+    runtime: Arc<ServiceRuntime<ApplicationService>>,
+    state: Arc<Application>,
+}
+
+#[async_graphql::Object]
+impl AtomaQuery {
+    /// Lists the models available from the Atoma network, together with the models allowed to
+    /// produce logged chat interactions.
+    ///
+    /// The set of allowed models is managed on the chain that created the application, so only
+    /// the service running on that chain reports which models are allowed.
+    async fn models(
+        &self,
+        api_token: Option<String>,
+        api_token_name: Option<String>,
+        api_token_key: Option<Vec<u8>>,
+        atoma_proxy_url: Option<String>,
+    ) -> async_graphql::Result<Vec<ModelInfo>> {
+        let api_token =
+            resolve_api_token(&self.state, api_token, api_token_name, api_token_key, None).await?;
+        let response = query_atoma(
+            &self.runtime,
+            atoma_proxy_url.as_deref().unwrap_or(ATOMA_CLOUD_URL),
+            &api_token,
+            &ModelsRequest,
+        )?;
+
+        let mut allowed_models = BTreeSet::new();
+        self.state
+            .allowed_models
+            .for_each_index(|model| {
+                allowed_models.insert(model);
+                Ok(())
+            })
+            .await?;
+
+        let mut models = response
+            .data
+            .into_iter()
+            .map(|model| ModelInfo {
+                is_default: model.id == DEFAULT_CHAT_MODEL,
+                is_allowed: allowed_models.remove(&model.id),
+                is_available: true,
+                id: model.id,
+                owned_by: model.owned_by,
+            })
+            .collect::<Vec<_>>();
+
+        models.extend(allowed_models.into_iter().map(|id| ModelInfo {
+            is_default: id == DEFAULT_CHAT_MODEL,
+            is_allowed: true,
+            is_available: false,
+            id,
+            owned_by: None,
+        }));
+
+        Ok(models)
+    }
+
+    /// Executes a chat completion using the Atoma Network without producing an operation to log
+    /// it.
+    ///
+    /// The arguments behave as in the `chat` mutation.
+    #[allow(clippy::too_many_arguments)]
+    async fn preview(
+        &self,
+        api_token: Option<String>,
+        api_token_name: Option<String>,
+        api_token_key: Option<Vec<u8>>,
+        message: ChatMessage,
+        model: Option<String>,
+        max_tokens: Option<usize>,
+        tools: Option<Vec<ChatTool>>,
+        response_schema: Option<async_graphql::Json<serde_json::Value>>,
+        atoma_proxy_url: Option<String>,
+    ) -> async_graphql::Result<ChatPreview> {
+        let api_token =
+            resolve_api_token(&self.state, api_token, api_token_name, api_token_key, None).await?;
+        let completion = request_chat_completion(
+            &self.runtime,
+            &api_token,
+            &message,
+            model,
+            max_tokens,
+            tools,
+            response_schema,
+            None,
+            atoma_proxy_url,
+        )?;
+        let first_response = completion
+            .responses
+            .into_iter()
+            .next()
+            .expect("Chat completion should have at least one response");
+
+        Ok(ChatPreview {
+            tool_calls: first_response.public_tool_calls(),
+            response: first_response.response,
+            model: completion.model,
+            usage: completion.usage,
+            is_verified: false,
+        })
+    }
+}
+
+/// An unverified response from the AI chat.
+#[derive(Clone, Debug, async_graphql::SimpleObject)]
+pub struct ChatPreview {
+    /// The AI's response.
+    response: String,
+    /// The model that produced the response.
+    model: String,
+    /// The number of tokens used by the chat completion, if reported by the Atoma proxy.
+    usage: Option<ChatUsage>,
+    /// The functions the AI requested to be called.
+    tool_calls: Vec<ToolCall>,
+    /// Always `false`, because previews are not logged, so they are never verified by the
+    /// contract.
+    is_verified: bool,
+}
+
+/// Information about a model available from the Atoma network.
+#[derive(Clone, Debug, async_graphql::SimpleObject)]
+pub struct ModelInfo {
+    /// The model's identifier, to be used when requesting inferences.
+    id: String,
+    /// The organization that owns the model.
+    owned_by: Option<String>,
+    /// If this is the model used for chat completions when none is specified.
+    is_default: bool,
+    /// If the model is listed by the Atoma proxy.
+    is_available: bool,
+    /// If the model is allowed to produce logged chat interactions.
+    is_allowed: bool,
+}
+
 /// Root type that defines all the GraphQL mutations available from the service.
 pub struct Mutation {
     runtime: Arc<ServiceRuntime<ApplicationService>>,
+    state: Arc<Application>,
 }
 
 #[async_graphql::Object]
 impl Mutation {
     /// Executes a chat completion using the Atoma Network.
+    ///
+    /// The request is authenticated with the `api_token`, or with the token stored on chain under
+    /// the `api_token_name`, which is unsealed with the X25519 secret `api_token_key`. The same
+    /// applies to all other queries and mutations that access the Atoma Network.
+    ///
+    /// The `api_token_key` is sent with every request that uses the stored token, so whoever runs
+    /// the service can unseal that token. It must be a key dedicated to the token, which can be
+    /// replaced together with the token if it leaks, and never the secret of the
+    /// `encryption_key`.
+    ///
+    /// The `privacy` mode determines how the interaction is stored on chain. Unless it is
+    /// [`PrivacyMode::Public`], the `encryption_seed` must be 32 random bytes generated by the
+    /// client and kept secret, from which the salts of the commitments to the contents are
+    /// derived. The salts are returned so that the interaction can later be verified with
+    /// `verifyChatInteraction`. When it is [`PrivacyMode::Encrypted`], the `encryption_key` must
+    /// be the chain owner's X25519 public key, and the ephemeral keys used to seal the contents
+    /// are also derived from the `encryption_seed`.
+    ///
+    /// The AI may respond with calls to any of the provided `tools`, which are logged together
+    /// with the interaction.
+    ///
+    /// If a `response_schema` is provided, the AI is asked to respond with JSON, and the response
+    /// is only logged if it is valid according to that JSON Schema.
+    ///
+    /// If a `choice_count` is provided, the AI is asked for that many responses, and the one at
+    /// the `choice` index is logged.
+    ///
+    /// The `payment` is taken from the chain's balance to pay the fee for verifying the
+    /// interaction, if the application charges one. Any excess is refunded.
+    ///
+    /// The operation doesn't include any node receipts. They can be attached afterwards with the
+    /// `attachNodeReceipts` mutation.
+    #[allow(clippy::too_many_arguments)]
     async fn chat(
         &self,
-        api_token: String,
+        api_token: Option<String>,
+        api_token_name: Option<String>,
+        api_token_key: Option<Vec<u8>>,
         message: ChatMessage,
         model: Option<String>,
         max_tokens: Option<usize>,
+        tools: Option<Vec<ChatTool>>,
+        response_schema: Option<async_graphql::Json<serde_json::Value>>,
+        choice_count: Option<usize>,
+        choice: Option<u32>,
         atoma_proxy_url: Option<String>,
-    ) -> async_graphql::Result<Vec<u8>> {
-        let request = ChatCompletionRequest {
-            stream: false,
-            messages: &[&message],
-            model: model.unwrap_or_else(|| "meta-llama/Llama-3.3-70B-Instruct".to_owned()),
-            max_tokens: max_tokens.unwrap_or(128),
+        privacy: Option<PrivacyMode>,
+        encryption_key: Option<Vec<u8>>,
+        encryption_seed: Option<Vec<u8>>,
+        payment: Option<Amount>,
+    ) -> async_graphql::Result<ChatResult> {
+        let api_token = resolve_api_token(
+            &self.state,
+            api_token,
+            api_token_name,
+            api_token_key,
+            encryption_key.as_deref(),
+        )
+        .await?;
+        let completion = self.complete_chat(
+            api_token,
+            message,
+            model,
+            max_tokens,
+            tools,
+            response_schema,
+            choice_count,
+            atoma_proxy_url,
+            privacy,
+            encryption_key,
+            encryption_seed,
+            payment,
+        )?;
+
+        let selected = ChatChoice::select(completion.choices, choice.unwrap_or(0))?;
+
+        Ok(ChatResult {
+            response: selected.response,
+            model: completion.model,
+            usage: completion.usage,
+            interaction_id: selected.interaction_id,
+            prompt_salt: selected.prompt_salt,
+            response_salt: selected.response_salt,
+            operation: encode_operation(&selected.operation),
+        })
+    }
+
+    /// Executes a chat completion using the Atoma Network, and schedules the operation to log
+    /// the interaction to be included in the block being built.
+    ///
+    /// Returns the ID of the [`ChatInteraction`] to be logged. The arguments behave as in the
+    /// `chat` mutation.
+    #[allow(clippy::too_many_arguments)]
+    async fn submit_chat(
+        &self,
+        api_token: Option<String>,
+        api_token_name: Option<String>,
+        api_token_key: Option<Vec<u8>>,
+        message: ChatMessage,
+        model: Option<String>,
+        max_tokens: Option<usize>,
+        tools: Option<Vec<ChatTool>>,
+        response_schema: Option<async_graphql::Json<serde_json::Value>>,
+        choice_count: Option<usize>,
+        choice: Option<u32>,
+        atoma_proxy_url: Option<String>,
+        privacy: Option<PrivacyMode>,
+        encryption_key: Option<Vec<u8>>,
+        encryption_seed: Option<Vec<u8>>,
+        payment: Option<Amount>,
+    ) -> async_graphql::Result<CryptoHash> {
+        let api_token = resolve_api_token(
+            &self.state,
+            api_token,
+            api_token_name,
+            api_token_key,
+            encryption_key.as_deref(),
+        )
+        .await?;
+        let completion = self.complete_chat(
+            api_token,
+            message,
+            model,
+            max_tokens,
+            tools,
+            response_schema,
+            choice_count,
+            atoma_proxy_url,
+            privacy,
+            encryption_key,
+            encryption_seed,
+            payment,
+        )?;
+        let selected = ChatChoice::select(completion.choices, choice.unwrap_or(0))?;
+        self.runtime.schedule_operation(&selected.operation);
+
+        Ok(selected.interaction_id)
+    }
+
+    /// Executes a chat completion using the Atoma Network, returning all of the `choice_count`
+    /// responses from the AI.
+    ///
+    /// Each choice includes its own operation to log it on chain, so that any number of them can
+    /// be logged. The remaining arguments behave as in the `chat` mutation.
+    #[allow(clippy::too_many_arguments)]
+    async fn chat_choices(
+        &self,
+        api_token: Option<String>,
+        api_token_name: Option<String>,
+        api_token_key: Option<Vec<u8>>,
+        message: ChatMessage,
+        model: Option<String>,
+        max_tokens: Option<usize>,
+        tools: Option<Vec<ChatTool>>,
+        response_schema: Option<async_graphql::Json<serde_json::Value>>,
+        choice_count: Option<usize>,
+        atoma_proxy_url: Option<String>,
+        privacy: Option<PrivacyMode>,
+        encryption_key: Option<Vec<u8>>,
+        encryption_seed: Option<Vec<u8>>,
+        payment: Option<Amount>,
+    ) -> async_graphql::Result<Vec<ChatChoice>> {
+        let api_token = resolve_api_token(
+            &self.state,
+            api_token,
+            api_token_name,
+            api_token_key,
+            encryption_key.as_deref(),
+        )
+        .await?;
+        let completion = self.complete_chat(
+            api_token,
+            message,
+            model,
+            max_tokens,
+            tools,
+            response_schema,
+            choice_count,
+            atoma_proxy_url,
+            privacy,
+            encryption_key,
+            encryption_seed,
+            payment,
+        )?;
+
+        Ok(completion.choices)
+    }
+
+    /// Computes an embedding vector for the `input` text using the Atoma Network.
+    ///
+    /// Node receipts can be attached to the returned operation with the `attachNodeReceipts`
+    /// mutation.
+    #[allow(clippy::too_many_arguments)]
+    async fn embed(
+        &self,
+        api_token: Option<String>,
+        api_token_name: Option<String>,
+        api_token_key: Option<Vec<u8>>,
+        input: String,
+        model: Option<String>,
+        atoma_proxy_url: Option<String>,
+    ) -> async_graphql::Result<Embedding> {
+        let api_token =
+            resolve_api_token(&self.state, api_token, api_token_name, api_token_key, None).await?;
+        let request = EmbeddingRequest {
+            model: model.unwrap_or_else(|| DEFAULT_EMBEDDING_MODEL.to_owned()),
+            input: &input,
         };
 
-        let response = self.query_chat_completion(
+        let response = query_atoma(
+            &self.runtime,
             atoma_proxy_url.as_deref().unwrap_or(ATOMA_CLOUD_URL),
             &api_token,
             &request,
         )?;
 
-        let interaction = ChatInteractionResponse::parse_from_completion_response(response)?
-            .with_prompt(message.content);
+        let record = response.into_record(&input)?;
+        let vector = record.vector.clone();
 
-        Ok(
-            bcs::to_bytes(&Operation::LogChatInteraction { interaction })
-                .expect("`LogChatInteraction` should be serializable"),
-        )
+        Ok(Embedding {
+            vector,
+            embedding_id: record.id(),
+            operation: encode_operation(&Operation::LogEmbedding {
+                embedding: record,
+                receipts: vec![],
+                quorum: 0,
+            }),
+        })
+    }
+
+    /// Generates images from the `prompt` using the Atoma Network.
+    ///
+    /// Node receipts can be attached to the returned operation with the `attachNodeReceipts`
+    /// mutation.
+    #[allow(clippy::too_many_arguments)]
+    async fn generate_image(
+        &self,
+        api_token: Option<String>,
+        api_token_name: Option<String>,
+        api_token_key: Option<Vec<u8>>,
+        prompt: String,
+        model: Option<String>,
+        count: Option<usize>,
+        size: Option<String>,
+        atoma_proxy_url: Option<String>,
+    ) -> async_graphql::Result<GeneratedImages> {
+        let api_token =
+            resolve_api_token(&self.state, api_token, api_token_name, api_token_key, None).await?;
+        let request = ImageGenerationRequest {
+            prompt: &prompt,
+            model: model.unwrap_or_else(|| DEFAULT_IMAGE_MODEL.to_owned()),
+            n: count.unwrap_or(1),
+            size: size.unwrap_or_else(|| "1024x1024".to_owned()),
+        };
+
+        let response = query_atoma(
+            &self.runtime,
+            atoma_proxy_url.as_deref().unwrap_or(ATOMA_CLOUD_URL),
+            &api_token,
+            &request,
+        )?;
+
+        ensure!(
+            !response.data.is_empty(),
+            async_graphql::Error::new("Image generation response has an empty `data` list")
+        );
+
+        let images = response
+            .data
+            .into_iter()
+            .map(|image| self.fetch_generated_image(image))
+            .collect::<async_graphql::Result<Vec<_>>>()?;
+
+        let model = request.model;
+        let generation = ImageGenerationRecord {
+            prompt,
+            model,
+            images: images
+                .iter()
+                .map(|image| ImageRecord {
+                    content_hash: image.content_hash,
+                    url: image.url.clone(),
+                })
+                .collect(),
+        };
+
+        Ok(GeneratedImages {
+            images,
+            generation_id: generation.id(),
+            operation: encode_operation(&Operation::LogImageGeneration {
+                generation,
+                receipts: vec![],
+                quorum: 0,
+            }),
+        })
     }
+
+    /// Attaches the `receipts` signed by the Atoma nodes that produced a response to the base64
+    /// encoded `operation` that logs it, returning the updated operation.
+    ///
+    /// Nodes sign the ID of the logged record, such as the `interactionId` returned by the
+    /// `chat` mutation, so their receipts can only be collected after the operation is built.
+    /// The record is only verified if at least `quorum` different nodes attest to it.
+    async fn attach_node_receipts(
+        &self,
+        operation: String,
+        receipts: Vec<NodeReceipt>,
+        quorum: u32,
+    ) -> async_graphql::Result<String> {
+        let mut operation = decode_operation(&operation)?;
+
+        let (record_id, choice_index) = match &operation {
+            Operation::LogChatInteraction { interaction, .. } => {
+                (interaction.id(), interaction.choice_index)
+            }
+            Operation::LogEmbedding { embedding, .. } => (embedding.id(), 0),
+            Operation::LogImageGeneration { generation, .. } => (generation.id(), 0),
+            _ => {
+                return Err(async_graphql::Error::new(
+                    "Only operations that log chat interactions, embeddings or image \
+                    generations can have node receipts",
+                ))
+            }
+        };
+
+        if let Some(position) = receipts
+            .iter()
+            .position(|receipt| !receipt.verify(record_id, choice_index))
+        {
+            return Err(async_graphql::Error::new(format!(
+                "Node receipt {position} is not signed for the logged record {record_id}"
+            )));
+        }
+
+        if let Operation::LogChatInteraction {
+            receipts: operation_receipts,
+            quorum: operation_quorum,
+            ..
+        }
+        | Operation::LogEmbedding {
+            receipts: operation_receipts,
+            quorum: operation_quorum,
+            ..
+        }
+        | Operation::LogImageGeneration {
+            receipts: operation_receipts,
+            quorum: operation_quorum,
+            ..
+        } = &mut operation
+        {
+            *operation_receipts = receipts;
+            *operation_quorum = quorum;
+        }
+
+        Ok(encode_operation(&operation))
+    }
+}
+
+/// The result of a `chat` mutation.
+#[derive(Clone, Debug, async_graphql::SimpleObject)]
+pub struct ChatResult {
+    /// The AI's response.
+    response: String,
+    /// The model that produced the response.
+    model: String,
+    /// The number of tokens used by the chat completion, if reported by the Atoma proxy.
+    usage: Option<ChatUsage>,
+    /// The ID of the [`ChatInteraction`] with the response.
+    interaction_id: CryptoHash,
+    /// The secret salt of the commitment to the prompt, if it is not public.
+    prompt_salt: Option<CryptoHash>,
+    /// The secret salt of the commitment to the response, if it is not public.
+    response_salt: Option<CryptoHash>,
+    /// The base64 encoded operation to log the interaction on chain.
+    operation: String,
+}
+
+/// A chat completion, with the operations to log each of its responses.
+#[derive(Clone, Debug)]
+pub struct ChatCompletion {
+    model: String,
+    usage: Option<ChatUsage>,
+    choices: Vec<ChatChoice>,
+}
+
+/// One of the responses to a chat completion.
+#[derive(Clone, Debug, async_graphql::SimpleObject)]
+#[graphql(complex)]
+pub struct ChatChoice {
+    /// The index of the choice in the chat completion response.
+    index: u32,
+    /// The AI's response.
+    response: String,
+    /// The ID of the [`ChatInteraction`] with this response.
+    interaction_id: CryptoHash,
+    /// The secret salt of the commitment to the prompt, if it is not public.
+    prompt_salt: Option<CryptoHash>,
+    /// The secret salt of the commitment to this response, if it is not public.
+    response_salt: Option<CryptoHash>,
+    /// The operation to log the interaction with this response on chain.
+    #[graphql(skip)]
+    operation: Operation,
+}
+
+#[async_graphql::ComplexObject]
+impl ChatChoice {
+    /// The base64 encoded operation to log the interaction with this response on chain.
+    #[graphql(name = "operation")]
+    async fn graphql_operation(&self) -> String {
+        encode_operation(&self.operation)
+    }
+}
+
+impl ChatChoice {
+    /// Selects the choice with the `index` from a list of `choices`.
+    pub fn select(choices: Vec<ChatChoice>, index: u32) -> async_graphql::Result<ChatChoice> {
+        choices
+            .into_iter()
+            .find(|candidate| candidate.index == index)
+            .ok_or_else(|| {
+                async_graphql::Error::new(format!(
+                    "Chat completion response has no choice with index {index}"
+                ))
+            })
+    }
+}
+
+/// Serializes an `operation` with BCS and encodes it in base64, so that it can be returned in
+/// GraphQL responses.
+fn encode_operation(operation: &Operation) -> String {
+    BASE64.encode(bcs::to_bytes(operation).expect("`Operation` should be serializable"))
+}
+
+/// Decodes an `operation` received as a GraphQL argument, encoded as by [`encode_operation`].
+fn decode_operation(operation: &str) -> async_graphql::Result<Operation> {
+    let bytes = BASE64.decode(operation).map_err(|error| {
+        async_graphql::Error::new(format!("Failed to decode base64 operation: {error}"))
+    })?;
+
+    bcs::from_bytes(&bytes).map_err(|error| {
+        async_graphql::Error::new(format!("Failed to deserialize operation: {error}"))
+    })
+}
+
+/// The result of a `generateImage` mutation.
+#[derive(Clone, Debug, async_graphql::SimpleObject)]
+pub struct GeneratedImages {
+    /// The generated images.
+    images: Vec<GeneratedImage>,
+    /// The ID of the [`ImageGenerationRecord`] to be logged, which is signed by the nodes that
+    /// attest to it.
+    generation_id: CryptoHash,
+    /// The base64 encoded operation to log the image generation on chain.
+    operation: String,
+}
+
+/// An image generated by the AI.
+#[derive(Clone, Debug, async_graphql::SimpleObject)]
+pub struct GeneratedImage {
+    /// The hash of the data blob with the image's contents.
+    content_hash: DataBlobHash,
+    /// The URL where the image was made available, if any.
+    url: Option<String>,
+    /// The base64 encoded image contents.
+    data: String,
+}
+
+/// The result of an `embed` mutation.
+#[derive(Clone, Debug, async_graphql::SimpleObject)]
+pub struct Embedding {
+    /// The computed embedding vector.
+    vector: Vec<f32>,
+    /// The ID of the [`EmbeddingRecord`] to be logged, which is signed by the nodes that attest
+    /// to it.
+    embedding_id: CryptoHash,
+    /// The base64 encoded operation to log the embedding on chain.
+    operation: String,
 }
 
 /// A message to be sent to the AI chat.
@@ -107,39 +991,332 @@ pub struct ChatMessage {
     name: Option<String>,
 }
 
-impl Mutation {
-    /// Queries the Atoma network for a chat completion.
-    fn query_chat_completion(
-        &self,
-        base_url: &str,
-        api_token: &str,
-        request: &ChatCompletionRequest,
-    ) -> async_graphql::Result<ChatCompletionResponse> {
-        let body = serde_json::to_vec(request)?;
-
-        let response = self.runtime.http_request(
-            http::Request::post(format!("{base_url}/v1/chat/completions"), body)
-                .with_header("Content-Type", b"application/json")
-                .with_header("Authorization", format!("Bearer {api_token}").as_bytes()),
-        );
+/// A tool that the AI may request to be called when responding to a chat message.
+#[derive(Clone, Debug, Serialize, async_graphql::InputObject)]
+pub struct ChatTool {
+    /// The name of the function.
+    name: String,
+    /// A description of what the function does, used by the AI to decide when to call it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    /// The JSON schema of the function's parameters.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    parameters: Option<async_graphql::Json<serde_json::Value>>,
+}
 
-        ensure!(
-            response.status == 200,
-            async_graphql::Error::new(format!(
-                "Failed to perform chat completion API query. Status code: {}",
+/// Queries an Atoma network API with the provided `request`.
+fn query_atoma<Request>(
+    runtime: &ServiceRuntime<ApplicationService>,
+    base_url: &str,
+    api_token: &str,
+    request: &Request,
+) -> async_graphql::Result<Request::Response>
+where
+    Request: AtomaRequest,
+{
+    let url = format!("{base_url}{}", Request::ENDPOINT);
+    let http_request = match Request::METHOD {
+        http::Method::Get => http::Request::get(url),
+        _ => http::Request::post(url, serde_json::to_vec(request)?)
+            .with_header("Content-Type", b"application/json"),
+    };
+
+    let response = runtime.http_request(
+        http_request.with_header("Authorization", format!("Bearer {api_token}").as_bytes()),
+    );
+
+    ensure!(
+        response.status == 200,
+        async_graphql::Error::new(redact_api_token(
+            format!(
+                "Failed to perform {} API query. Status code: {}",
+                Request::NAME,
                 response.status
-            ))
-        );
+            ),
+            api_token,
+        ))
+    );
 
-        serde_json::from_slice::<ChatCompletionResponse>(&response.body).map_err(|error| {
-            async_graphql::Error::new(format!(
-                "Failed to deserialize chat completion response: {error}\n{:?}",
+    serde_json::from_slice::<Request::Response>(&response.body).map_err(|error| {
+        async_graphql::Error::new(redact_api_token(
+            format!(
+                "Failed to deserialize {} response: {error}\n{:?}",
+                Request::NAME,
                 String::from_utf8_lossy(&response.body),
-            ))
+            ),
+            api_token,
+        ))
+    })
+}
+
+/// Resolves the Atoma API token to use for a request.
+///
+/// The token is either provided directly as the `api_token`, or is the token stored on chain
+/// under the `api_token_name`, which is unsealed with the X25519 secret `api_token_key`.
+///
+/// The `api_token_key` must be the secret of the key dedicated to the stored token, so it is
+/// rejected if it is the secret of the `encryption_key` that the request's chat interactions are
+/// sealed to.
+async fn resolve_api_token(
+    state: &Application,
+    api_token: Option<String>,
+    api_token_name: Option<String>,
+    api_token_key: Option<Vec<u8>>,
+    encryption_key: Option<&[u8]>,
+) -> async_graphql::Result<String> {
+    match (api_token, api_token_name) {
+        (Some(_), Some(_)) => Err(async_graphql::Error::new(
+            "Provide either an API token or the name of a stored API token, not both",
+        )),
+        (None, None) => Err(async_graphql::Error::new(
+            "Provide either an API token or the name of a stored API token",
+        )),
+        (Some(api_token), None) => Ok(api_token),
+        (None, Some(name)) => {
+            let key_bytes = api_token_key.ok_or_else(|| {
+                async_graphql::Error::new("Stored API tokens require the key to unseal them")
+            })?;
+            let secret_key = <[u8; crypto_box::KEY_SIZE]>::try_from(key_bytes.as_slice())
+                .map(crypto_box::SecretKey::from_bytes)
+                .map_err(|_| {
+                    async_graphql::Error::new(format!(
+                        "API token key must have {} bytes",
+                        crypto_box::KEY_SIZE
+                    ))
+                })?;
+            let public_key = secret_key.public_key().to_bytes();
+            ensure!(
+                encryption_key != Some(public_key.as_slice()),
+                async_graphql::Error::new(
+                    "The API token key must not be the secret of the encryption key"
+                )
+            );
+            let sealed_token = state.api_tokens.get(&name).await?.ok_or_else(|| {
+                async_graphql::Error::new(format!("No API token named {name:?} is stored"))
+            })?;
+            ensure!(
+                sealed_token.key == public_key,
+                async_graphql::Error::new(format!(
+                    "The API token key is not the key that the API token named {name:?} is \
+                    sealed to"
+                ))
+            );
+            let token = secret_key.unseal(&sealed_token.ciphertext).map_err(|_| {
+                async_graphql::Error::new(format!("Failed to unseal the API token named {name:?}"))
+            })?;
+
+            String::from_utf8(token).map_err(|_| {
+                async_graphql::Error::new(format!(
+                    "The API token named {name:?} is not valid UTF-8"
+                ))
+            })
+        }
+    }
+}
+
+/// Removes any occurrences of the `api_token` from an error `message`.
+fn redact_api_token(message: String, api_token: &str) -> String {
+    if api_token.is_empty() {
+        message
+    } else {
+        message.replace(api_token, "[redacted]")
+    }
+}
+
+/// Requests a chat completion from the Atoma network, checking that the responses follow the
+/// `response_schema` if one is provided.
+#[allow(clippy::too_many_arguments)]
+fn request_chat_completion(
+    runtime: &ServiceRuntime<ApplicationService>,
+    api_token: &str,
+    message: &ChatMessage,
+    model: Option<String>,
+    max_tokens: Option<usize>,
+    tools: Option<Vec<ChatTool>>,
+    response_schema: Option<async_graphql::Json<serde_json::Value>>,
+    choice_count: Option<usize>,
+    atoma_proxy_url: Option<String>,
+) -> async_graphql::Result<ChatResponses> {
+    let response_schema = response_schema
+        .map(|schema| ResponseSchema::new(schema.0))
+        .transpose()
+        .map_err(|error| StructuredOutputError::InvalidSchema(error).extend())?;
+
+    let request = ChatCompletionRequest {
+        stream: false,
+        messages: &[message],
+        model: model.unwrap_or_else(|| DEFAULT_CHAT_MODEL.to_owned()),
+        max_tokens: max_tokens.unwrap_or(128),
+        n: choice_count,
+        tools: tools
+            .iter()
+            .flatten()
+            .map(|tool| ChatToolDefinition {
+                kind: "function",
+                function: tool,
+            })
+            .collect(),
+        response_format: response_schema.as_ref().map(ResponseFormat::json_schema),
+    };
+
+    let response = query_atoma(
+        runtime,
+        atoma_proxy_url.as_deref().unwrap_or(ATOMA_CLOUD_URL),
+        api_token,
+        &request,
+    )?;
+
+    let usage = response.usage;
+    let responses = ChatInteractionResponse::parse_all_from_completion_response(response)?;
+
+    if let Some(schema) = &response_schema {
+        for interaction_response in &responses {
+            interaction_response.validate(schema)?;
+        }
+    }
+
+    Ok(ChatResponses {
+        model: request.model,
+        usage,
+        responses,
+    })
+}
+
+/// The responses to a chat completion request.
+#[derive(Clone, Debug)]
+pub struct ChatResponses {
+    model: String,
+    usage: Option<ChatUsage>,
+    responses: Vec<ChatInteractionResponse>,
+}
+
+impl Mutation {
+    /// Executes a chat completion using the Atoma Network, and prepares the operations to log
+    /// each of the choices in the response.
+    #[allow(clippy::too_many_arguments)]
+    fn complete_chat(
+        &self,
+        api_token: String,
+        message: ChatMessage,
+        model: Option<String>,
+        max_tokens: Option<usize>,
+        tools: Option<Vec<ChatTool>>,
+        response_schema: Option<async_graphql::Json<serde_json::Value>>,
+        choice_count: Option<usize>,
+        atoma_proxy_url: Option<String>,
+        privacy: Option<PrivacyMode>,
+        encryption_key: Option<Vec<u8>>,
+        encryption_seed: Option<Vec<u8>>,
+        payment: Option<Amount>,
+    ) -> async_graphql::Result<ChatCompletion> {
+        let privacy = ChatPrivacy::new(
+            privacy.unwrap_or_default(),
+            encryption_key.as_deref(),
+            encryption_seed.as_deref(),
+        )?;
+        let completion = request_chat_completion(
+            &self.runtime,
+            &api_token,
+            &message,
+            model,
+            max_tokens,
+            tools,
+            response_schema,
+            choice_count,
+            atoma_proxy_url,
+        )?;
+
+        let choices = completion
+            .responses
+            .into_iter()
+            .map(|interaction_response| {
+                let index = interaction_response.index;
+                let response = interaction_response.response.clone();
+                let prompt_salt = privacy.salt(&message.content);
+                let response_salt = privacy.salt(&response);
+                let interaction = interaction_response.with_prompt(
+                    completion.model.clone(),
+                    message.content.clone(),
+                    &privacy,
+                );
+
+                ChatChoice {
+                    index,
+                    response,
+                    interaction_id: interaction.id(),
+                    prompt_salt,
+                    response_salt,
+                    operation: Operation::LogChatInteraction {
+                        interaction,
+                        payment: payment.unwrap_or(Amount::ZERO),
+                        receipts: vec![],
+                        quorum: 0,
+                    },
+                }
+            })
+            .collect();
+
+        Ok(ChatCompletion {
+            model: completion.model,
+            usage: completion.usage,
+            choices,
+        })
+    }
+
+    /// Obtains the contents of a generated `image`, downloading it if necessary.
+    fn fetch_generated_image(
+        &self,
+        image: ImageGenerationData,
+    ) -> async_graphql::Result<GeneratedImage> {
+        let bytes = match (&image.b64_json, &image.url) {
+            (Some(encoded), _) => BASE64.decode(encoded).map_err(|error| {
+                async_graphql::Error::new(format!("Failed to decode generated image: {error}"))
+            })?,
+            (None, Some(url)) => {
+                let response = self.runtime.http_request(http::Request::get(url));
+
+                ensure!(
+                    response.status == 200,
+                    async_graphql::Error::new(format!(
+                        "Failed to download generated image. Status code: {}",
+                        response.status
+                    ))
+                );
+
+                response.body
+            }
+            (None, None) => {
+                return Err(async_graphql::Error::new(
+                    "Generated image has neither contents nor a URL",
+                ))
+            }
+        };
+
+        Ok(GeneratedImage {
+            content_hash: DataBlobHash(Blob::new_data(bytes.clone()).id().hash),
+            url: image.url,
+            data: BASE64.encode(bytes),
         })
     }
 }
 
+/// A request body to be sent to an Atoma network API.
+pub trait AtomaRequest: Serialize {
+    /// The path of the API endpoint.
+    const ENDPOINT: &'static str;
+
+    /// The HTTP method used to query the API endpoint.
+    ///
+    /// The request is only sent as a JSON body for methods other than GET.
+    const METHOD: http::Method = http::Method::Post;
+
+    /// A human readable name of the API, used in error messages.
+    const NAME: &'static str;
+
+    /// The response received from the API.
+    type Response: DeserializeOwned;
+}
+
 /// The POST body to be sent to the chat completion API.
 #[derive(Clone, Debug, Serialize)]
 pub struct ChatCompletionRequest<'message> {
@@ -147,32 +1324,122 @@ pub struct ChatCompletionRequest<'message> {
     messages: &'message [&'message ChatMessage],
     model: String,
     max_tokens: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    n: Option<usize>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<ChatToolDefinition<'message>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_format: Option<ResponseFormat<'message>>,
+}
+
+/// The format the chat completion API should use for its response.
+#[derive(Clone, Debug, Serialize)]
+pub struct ResponseFormat<'schema> {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    json_schema: ResponseJsonSchema<'schema>,
+}
+
+/// The JSON Schema that a response in a structured output format should follow.
+#[derive(Clone, Debug, Serialize)]
+pub struct ResponseJsonSchema<'schema> {
+    name: &'static str,
+    schema: &'schema serde_json::Value,
+}
+
+impl<'schema> ResponseFormat<'schema> {
+    /// Creates a [`ResponseFormat`] requesting a JSON response that follows the `schema`.
+    pub fn json_schema(schema: &'schema ResponseSchema) -> Self {
+        ResponseFormat {
+            kind: "json_schema",
+            json_schema: ResponseJsonSchema {
+                name: "response",
+                schema: schema.as_json(),
+            },
+        }
+    }
+}
+
+/// The definition of a [`ChatTool`] as expected by the chat completion API.
+#[derive(Clone, Debug, Serialize)]
+pub struct ChatToolDefinition<'tool> {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    function: &'tool ChatTool,
+}
+
+impl AtomaRequest for ChatCompletionRequest<'_> {
+    const ENDPOINT: &'static str = "/v1/chat/completions";
+    const NAME: &'static str = "chat completion";
+
+    type Response = ChatCompletionResponse;
 }
 
 /// The response received from the chat completion API.
 #[derive(Clone, Debug, Deserialize)]
 pub struct ChatCompletionResponse {
     choices: Vec<ChatCompletionChoice>,
+    #[serde(default)]
+    usage: Option<ChatUsage>,
+}
+
+/// The number of tokens used by a chat completion.
+#[derive(Clone, Copy, Debug, Deserialize, async_graphql::SimpleObject)]
+pub struct ChatUsage {
+    /// The number of tokens in the prompt.
+    prompt_tokens: u64,
+    /// The number of tokens in the generated responses.
+    #[serde(default)]
+    completion_tokens: u64,
+    /// The total number of tokens used.
+    total_tokens: u64,
 }
 
 /// A choice received in the response from a chat completion API.
 #[derive(Clone, Debug, Deserialize)]
 pub struct ChatCompletionChoice {
-    message: ChatMessage,
+    #[serde(default)]
+    index: Option<u32>,
+    message: ChatCompletionMessage,
+}
+
+/// A message received in the response from a chat completion API.
+#[derive(Clone, Debug, Deserialize)]
+pub struct ChatCompletionMessage {
+    #[serde(default)]
+    content: Option<String>,
+    #[serde(default)]
+    tool_calls: Vec<ChatCompletionToolCall>,
+}
+
+/// A tool call received in the response from a chat completion API.
+#[derive(Clone, Debug, Deserialize)]
+pub struct ChatCompletionToolCall {
+    id: String,
+    function: ChatCompletionFunctionCall,
+}
+
+/// The function to be called by a [`ChatCompletionToolCall`].
+#[derive(Clone, Debug, Deserialize)]
+pub struct ChatCompletionFunctionCall {
+    name: String,
+    arguments: String,
 }
 
 /// Only the response for a [`ChatInteraction`].
 #[derive(Clone, Debug)]
 pub struct ChatInteractionResponse {
+    index: u32,
     response: String,
+    tool_calls: Vec<ChatCompletionToolCall>,
 }
 
 impl ChatInteractionResponse {
-    /// Parses the first choice from a [`ChatCompletionResponse`] to extract the
-    /// [`ChatInteractionResponse`].
-    pub fn parse_from_completion_response(
+    /// Parses all choices from a [`ChatCompletionResponse`] to extract a
+    /// [`ChatInteractionResponse`] for each of them.
+    pub fn parse_all_from_completion_response(
         response: ChatCompletionResponse,
-    ) -> async_graphql::Result<Self> {
+    ) -> async_graphql::Result<Vec<Self>> {
         ensure!(
             !response.choices.is_empty(),
             async_graphql::Error::new(
@@ -180,25 +1447,390 @@ impl ChatInteractionResponse {
             )
         );
 
-        let first_choice = response
+        response
             .choices
             .into_iter()
-            .next()
-            .expect("Response should have at least one choice element");
+            .enumerate()
+            .map(|(position, choice)| {
+                let index = match choice.index {
+                    Some(index) => index,
+                    None => u32::try_from(position).map_err(|_| {
+                        async_graphql::Error::new("Chat completion response has too many choices")
+                    })?,
+                };
 
-        Ok(ChatInteractionResponse {
-            response: first_choice.message.content,
-        })
+                Ok(ChatInteractionResponse {
+                    index,
+                    response: choice.message.content.unwrap_or_default(),
+                    tool_calls: choice.message.tool_calls,
+                })
+            })
+            .collect()
     }
 
-    /// Builds a [`ChatInteraction`] using this response and the provided `prompt`.
-    pub fn with_prompt(self, prompt: String) -> ChatInteraction {
+    /// Checks if the response is a JSON document that follows the `schema`.
+    pub fn validate(&self, schema: &ResponseSchema) -> async_graphql::Result<()> {
+        let document = serde_json::from_str::<serde_json::Value>(&self.response)
+            .map_err(|error| StructuredOutputError::InvalidJson(error.to_string()).extend())?;
+        let violations = schema.violations(&document);
+
+        ensure!(
+            violations.is_empty(),
+            StructuredOutputError::SchemaMismatch(violations).extend()
+        );
+
+        Ok(())
+    }
+
+    /// Returns the tool calls in this response, with their arguments in plaintext.
+    pub fn public_tool_calls(&self) -> Vec<ToolCall> {
+        self.tool_calls
+            .iter()
+            .map(|tool_call| ToolCall {
+                id: tool_call.id.clone(),
+                name: tool_call.function.name.clone(),
+                arguments: ChatContent::Public(tool_call.function.arguments.clone()),
+            })
+            .collect()
+    }
+
+    /// Builds a [`ChatInteraction`] using this response, the `model` that produced it and the
+    /// provided `prompt`, storing their contents according to the configured [`ChatPrivacy`].
+    pub fn with_prompt(
+        self,
+        model: String,
+        prompt: String,
+        privacy: &ChatPrivacy,
+    ) -> ChatInteraction {
         ChatInteraction {
-            prompt,
-            response: self.response,
+            model,
+            prompt: privacy.protect(prompt),
+            response: privacy.protect(self.response),
+            tool_calls: self
+                .tool_calls
+                .into_iter()
+                .map(|tool_call| ToolCall {
+                    id: tool_call.id,
+                    name: tool_call.function.name,
+                    arguments: privacy.protect(tool_call.function.arguments),
+                })
+                .collect(),
+            choice_index: self.index,
+        }
+    }
+}
+
+/// Errors when requesting a structured JSON response from the chat completion API.
+#[derive(Clone, Debug)]
+pub enum StructuredOutputError {
+    /// The provided response schema is not a valid JSON Schema.
+    InvalidSchema(String),
+
+    /// The response is not a JSON document.
+    InvalidJson(String),
+
+    /// The response does not follow the response schema.
+    SchemaMismatch(Vec<String>),
+}
+
+impl StructuredOutputError {
+    /// The error code reported in the GraphQL error's extensions.
+    pub fn code(&self) -> &'static str {
+        match self {
+            StructuredOutputError::InvalidSchema(_) => "INVALID_RESPONSE_SCHEMA",
+            StructuredOutputError::InvalidJson(_) => "INVALID_JSON_RESPONSE",
+            StructuredOutputError::SchemaMismatch(_) => "RESPONSE_SCHEMA_MISMATCH",
         }
     }
 }
 
+impl fmt::Display for StructuredOutputError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StructuredOutputError::InvalidSchema(error) => {
+                write!(formatter, "Invalid response schema: {error}")
+            }
+            StructuredOutputError::InvalidJson(error) => {
+                write!(
+                    formatter,
+                    "Chat completion response is not valid JSON: {error}"
+                )
+            }
+            StructuredOutputError::SchemaMismatch(violations) => write!(
+                formatter,
+                "Chat completion response does not match the response schema: {}",
+                violations.join("; ")
+            ),
+        }
+    }
+}
+
+impl ErrorExtensions for StructuredOutputError {
+    fn extend(&self) -> async_graphql::Error {
+        async_graphql::Error::new(self.to_string()).extend_with(|_, extensions| {
+            extensions.set("code", self.code());
+
+            if let StructuredOutputError::SchemaMismatch(violations) = self {
+                extensions.set("violations", violations.clone());
+            }
+        })
+    }
+}
+
+/// How the contents of a [`ChatInteraction`] should be stored on chain.
+pub enum ChatPrivacy {
+    /// The plaintext contents are stored.
+    Public,
+
+    /// Only commitments to the contents are stored, salted with secrets derived from the client's
+    /// secret `seed`.
+    Hashed { seed: [u8; SEALING_SEED_SIZE] },
+
+    /// The contents are sealed to the `recipient`'s key, with ephemeral keys and commitment salts
+    /// derived from the client's secret `seed`.
+    Encrypted {
+        recipient: crypto_box::PublicKey,
+        seed: [u8; SEALING_SEED_SIZE],
+    },
+}
+
+impl ChatPrivacy {
+    /// Creates a [`ChatPrivacy`] configuration for the requested [`PrivacyMode`].
+    pub fn new(
+        mode: PrivacyMode,
+        encryption_key: Option<&[u8]>,
+        encryption_seed: Option<&[u8]>,
+    ) -> async_graphql::Result<Self> {
+        match mode {
+            PrivacyMode::Public => Ok(ChatPrivacy::Public),
+            PrivacyMode::Hashed => Ok(ChatPrivacy::Hashed {
+                seed: parse_encryption_seed("Hashed", encryption_seed)?,
+            }),
+            PrivacyMode::Encrypted => {
+                let key_bytes = encryption_key.ok_or_else(|| {
+                    async_graphql::Error::new(
+                        "Encrypted chat interactions require an encryption key",
+                    )
+                })?;
+                let recipient = <[u8; crypto_box::KEY_SIZE]>::try_from(key_bytes)
+                    .map_err(|_| {
+                        async_graphql::Error::new(format!(
+                            "Encryption key must have {} bytes",
+                            crypto_box::KEY_SIZE
+                        ))
+                    })?
+                    .into();
+                let seed = parse_encryption_seed("Encrypted", encryption_seed)?;
+
+                Ok(ChatPrivacy::Encrypted { recipient, seed })
+            }
+        }
+    }
+
+    /// Derives the secret salt of the commitment to the `plaintext`, if it is not public.
+    ///
+    /// The plaintext is mixed in so that different contents never share the same salt.
+    pub fn salt(&self, plaintext: &str) -> Option<CryptoHash> {
+        match self {
+            ChatPrivacy::Public => None,
+            ChatPrivacy::Hashed { seed } | ChatPrivacy::Encrypted { seed, .. } => {
+                Some(CryptoHash::new(&CommitmentSalt {
+                    secret: *seed,
+                    text: plaintext,
+                }))
+            }
+        }
+    }
+
+    /// Converts the `plaintext` into the [`ChatContent`] to be stored on chain.
+    pub fn protect(&self, plaintext: String) -> ChatContent {
+        match self {
+            ChatPrivacy::Public => ChatContent::Public(plaintext),
+            ChatPrivacy::Hashed { .. } => {
+                let salt = self
+                    .salt(&plaintext)
+                    .expect("Hashed contents should be salted");
+                ChatContent::Hashed(ChatContent::commit_salted(&plaintext, salt))
+            }
+            ChatPrivacy::Encrypted { recipient, seed } => {
+                let salt = self
+                    .salt(&plaintext)
+                    .expect("Encrypted contents should be salted");
+                let commitment = ChatContent::commit_salted(&plaintext, salt);
+                // Services have no source of entropy, so the ephemeral key is derived from the
+                // client's secret seed. The commitment is mixed in so that different contents
+                // are never sealed with the same ephemeral key.
+                let seed = CryptoHash::new(&SealingSeed {
+                    secret: *seed,
+                    recipient: recipient.to_bytes(),
+                    commitment,
+                });
+                let mut rng = ChaCha20Rng::from_seed(seed.as_bytes().0);
+                let ciphertext = recipient
+                    .seal(&mut rng, plaintext.as_bytes())
+                    .expect("Sealing contents in memory should not fail");
+
+                ChatContent::Encrypted {
+                    ciphertext,
+                    commitment,
+                }
+            }
+        }
+    }
+}
+
+/// The size of the secret seed provided by clients to salt the commitments to
+/// [`ChatContent::Hashed`] and [`ChatContent::Encrypted`] contents, and to seal the latter.
+pub const SEALING_SEED_SIZE: usize = 32;
+
+/// Parses the client's secret `encryption_seed`, required by the `mode` named in the errors.
+fn parse_encryption_seed(
+    mode: &str,
+    encryption_seed: Option<&[u8]>,
+) -> async_graphql::Result<[u8; SEALING_SEED_SIZE]> {
+    let seed_bytes = encryption_seed.ok_or_else(|| {
+        async_graphql::Error::new(format!(
+            "{mode} chat interactions require an encryption seed"
+        ))
+    })?;
+
+    <[u8; SEALING_SEED_SIZE]>::try_from(seed_bytes).map_err(|_| {
+        async_graphql::Error::new(format!(
+            "Encryption seed must have {SEALING_SEED_SIZE} bytes"
+        ))
+    })
+}
+
+/// The values used to derive the secret salt of the commitment to a [`ChatContent`].
+#[derive(Deserialize, Serialize)]
+struct CommitmentSalt<'text> {
+    secret: [u8; SEALING_SEED_SIZE],
+    text: &'text str,
+}
+
+impl<'text> BcsHashable<'text> for CommitmentSalt<'text> {}
+
+/// The values used to derive the ephemeral key to seal [`ChatContent::Encrypted`] contents.
+#[derive(Deserialize, Serialize)]
+struct SealingSeed {
+    secret: [u8; SEALING_SEED_SIZE],
+    recipient: [u8; crypto_box::KEY_SIZE],
+    commitment: CryptoHash,
+}
+
+impl BcsHashable<'_> for SealingSeed {}
+
+/// The POST body to be sent to the embeddings API.
+#[derive(Clone, Debug, Serialize)]
+pub struct EmbeddingRequest<'input> {
+    model: String,
+    input: &'input str,
+}
+
+impl AtomaRequest for EmbeddingRequest<'_> {
+    const ENDPOINT: &'static str = "/v1/embeddings";
+    const NAME: &'static str = "embeddings";
+
+    type Response = EmbeddingResponse;
+}
+
+/// The response received from the embeddings API.
+#[derive(Clone, Debug, Deserialize)]
+pub struct EmbeddingResponse {
+    model: String,
+    data: Vec<EmbeddingData>,
+}
+
+/// An embedding received in the response from the embeddings API.
+#[derive(Clone, Debug, Deserialize)]
+pub struct EmbeddingData {
+    embedding: Vec<f32>,
+}
+
+impl EmbeddingResponse {
+    /// Builds the [`EmbeddingRecord`] for the `input` from the first embedding in this
+    /// response.
+    pub fn into_record(self, input: &str) -> async_graphql::Result<EmbeddingRecord> {
+        let first_embedding = self.data.into_iter().next().ok_or_else(|| {
+            async_graphql::Error::new("Embeddings response has an empty `data` list")
+        })?;
+        let dimensions = u32::try_from(first_embedding.embedding.len())
+            .map_err(|_| async_graphql::Error::new("Embedding has too many dimensions"))?;
+
+        Ok(EmbeddingRecord {
+            input_hash: ChatContent::commit(input),
+            model: self.model,
+            dimensions,
+            vector: first_embedding.embedding,
+        })
+    }
+}
+
+/// The POST body to be sent to the image generation API.
+#[derive(Clone, Debug, Serialize)]
+pub struct ImageGenerationRequest<'prompt> {
+    prompt: &'prompt str,
+    model: String,
+    n: usize,
+    size: String,
+}
+
+impl AtomaRequest for ImageGenerationRequest<'_> {
+    const ENDPOINT: &'static str = "/v1/images/generations";
+    const NAME: &'static str = "image generation";
+
+    type Response = ImageGenerationResponse;
+}
+
+/// The response received from the image generation API.
+#[derive(Clone, Debug, Deserialize)]
+pub struct ImageGenerationResponse {
+    data: Vec<ImageGenerationData>,
+}
+
+/// An image received in the response from the image generation API.
+#[derive(Clone, Debug, Deserialize)]
+pub struct ImageGenerationData {
+    #[serde(default)]
+    url: Option<String>,
+    #[serde(default)]
+    b64_json: Option<String>,
+}
+
+/// The request for the list of models available from the Atoma network.
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct ModelsRequest;
+
+impl AtomaRequest for ModelsRequest {
+    const ENDPOINT: &'static str = "/v1/models";
+    const METHOD: http::Method = http::Method::Get;
+    const NAME: &'static str = "models";
+
+    type Response = ModelsResponse;
+}
+
+/// The response received from the models API.
+#[derive(Clone, Debug, Deserialize)]
+pub struct ModelsResponse {
+    data: Vec<ModelData>,
+}
+
+/// A model received in the response from the models API.
+#[derive(Clone, Debug, Deserialize)]
+pub struct ModelData {
+    id: String,
+    #[serde(default)]
+    owned_by: Option<String>,
+}
+
+/// The model used for chat completions if none is specified.
+const DEFAULT_CHAT_MODEL: &str = "meta-llama/Llama-3.3-70B-Instruct";
+
+/// The model used to generate images if none is specified.
+const DEFAULT_IMAGE_MODEL: &str = "black-forest-labs/FLUX.1-schnell";
+
+/// The model used to compute embeddings if none is specified.
+const DEFAULT_EMBEDDING_MODEL: &str = "intfloat/multilingual-e5-large-instruct";
+
 /// The base URL to access the Atoma Cloud proxy.
 const ATOMA_CLOUD_URL: &str = "https://api.atoma.network";
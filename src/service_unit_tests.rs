@@ -1,42 +1,56 @@
 // Copyright (c) Zefchain Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
-use std::{collections::HashSet, sync::Arc};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    str::FromStr,
+    sync::Arc,
+};
 
-use atoma_demo::{ChatInteraction, Operation, PublicKey};
+use atoma_demo::{
+    merkle::ChatLogInclusionProof, ApiToken, BlockTime, ChatContent, ChatInteraction, ChatLogEntry,
+    EmbeddingRecord, ImageGenerationRecord, ImageRecord, LoggedChatInteraction, NodeAttestation,
+    NodeReceipt, NodeResponse, NodeStats, NodeSuspension, Operation, PrivacyMode, PublicKey,
+    Signature, SuspensionReason, TeePlatform, ToolCall,
+};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use ed25519_dalek::{Signer, SigningKey};
 use linera_sdk::{
     bcs, http,
+    linera_base_types::{AccountOwner, Amount, Blob, BlockHeight, CryptoHash, Timestamp},
     util::BlockingWait,
     views::{RootView, View},
-    Service, ServiceRuntime, ViewStorageContext,
+    DataBlobHash, Service, ServiceRuntime, ViewStorageContext,
 };
+use proptest::prelude::{any, prop_assume, prop_oneof, Just, Strategy};
+use rand_chacha::{rand_core::SeedableRng, ChaCha20Rng};
+use serde::de::DeserializeOwned;
 use serde_json::json;
 use test_strategy::proptest;
 
-use super::{state::Application, ApplicationService, ATOMA_CLOUD_URL};
+use super::{
+    state::Application, ApplicationService, ATOMA_CLOUD_URL, DEFAULT_CHAT_MODEL,
+    DEFAULT_EMBEDDING_MODEL, DEFAULT_IMAGE_MODEL, SEALING_SEED_SIZE,
+};
 
 /// Tests if the chat logged on chain can be inspected with GraphQL.
 #[proptest]
-fn read_chat_log(interactions: Vec<ChatInteraction>) {
-    let runtime = ServiceRuntime::new();
-    let storage = runtime.key_value_store().to_mut();
-
-    let mut initial_state = Application::load(ViewStorageContext::new_unsafe(storage, vec![], ()))
-        .blocking_wait()
-        .expect("Failed to load state from mock storage");
-
-    for interaction in interactions.iter().cloned() {
-        initial_state.chat_log.push(interaction);
-    }
-
-    initial_state
-        .save()
-        .blocking_wait()
-        .expect("Failed to save initial state to mock storage");
+fn read_chat_log(interactions: Vec<LoggedChatInteraction>) {
+    let service = setup_service_with_chat_log(&interactions);
 
-    let service = setup_service(runtime);
-
-    let request = async_graphql::Request::new("query { chatLog { entries { prompt, response } } }");
+    let request = async_graphql::Request::new(
+        "query { chatLog { entries { ... on LoggedChatInteraction { \
+            model, \
+            prompt { privacy, text, commitment, ciphertext }, \
+            response { privacy, text, commitment, ciphertext }, \
+            toolCalls { id, name, arguments { privacy, text, commitment, ciphertext } }, \
+            choiceIndex, \
+            requester, \
+            requesterChainId, \
+            requestedAt { timestamp, blockHeight }, \
+            verifiedAt { timestamp, blockHeight } \
+        } } } }",
+    );
 
     let response = service.handle_query(request).blocking_wait();
 
@@ -56,16 +70,32 @@ fn read_chat_log(interactions: Vec<ChatInteraction>) {
             let async_graphql::Value::Object(entry) = entry_value else {
                 panic!("Unexpected interaction entry type");
             };
-            let async_graphql::Value::String(ref prompt) = entry["prompt"] else {
-                panic!("Unexpected interaction prompt type");
+
+            let async_graphql::Value::String(ref model) = entry["model"] else {
+                panic!("Unexpected interaction model type");
+            };
+            let async_graphql::Value::List(ref tool_calls) = entry["toolCalls"] else {
+                panic!("Unexpected interaction tool calls type");
             };
-            let async_graphql::Value::String(ref response) = entry["response"] else {
-                panic!("Unexpected interaction response type");
+            let async_graphql::Value::Number(ref choice_index) = entry["choiceIndex"] else {
+                panic!("Unexpected interaction choice index type");
             };
 
-            ChatInteraction {
-                prompt: prompt.clone(),
-                response: response.clone(),
+            LoggedChatInteraction {
+                interaction: ChatInteraction {
+                    model: model.clone(),
+                    prompt: parse_chat_content(&entry["prompt"]),
+                    response: parse_chat_content(&entry["response"]),
+                    tool_calls: tool_calls.iter().map(parse_tool_call).collect(),
+                    choice_index: choice_index
+                        .as_u64()
+                        .and_then(|index| index.try_into().ok())
+                        .expect("Invalid interaction choice index"),
+                },
+                requester: parse_scalar(&entry["requester"]),
+                requester_chain_id: parse_scalar(&entry["requesterChainId"]),
+                requested_at: parse_block_time(&entry["requestedAt"]),
+                verified_at: parse_block_time(&entry["verifiedAt"]),
             }
         })
         .collect::<Vec<_>>();
@@ -73,6 +103,363 @@ fn read_chat_log(interactions: Vec<ChatInteraction>) {
     assert_eq!(persisted_interactions, interactions);
 }
 
+/// Tests if redacted chat log entries are shown distinctly with GraphQL, and can't be verified.
+#[proptest]
+fn show_redacted_chat_log_entries(
+    #[any(proptest::sample::size_range(1..10).lift())] entries: Vec<ChatLogEntry>,
+    #[strategy(0..#entries.len())] index: usize,
+) {
+    let service = setup_service_with_chat_log_entries(&entries);
+
+    let request = async_graphql::Request::new(
+        "query { chatLog { entries { \
+            __typename, \
+            ... on LoggedChatInteraction { id }, \
+            ... on RedactedChatInteraction { entryHash, interactionId } \
+        } } }",
+    );
+
+    let response = service.handle_query(request).blocking_wait();
+
+    let expected_entries = entries
+        .iter()
+        .map(|entry| match entry {
+            ChatLogEntry::Logged(logged_interaction) => json!({
+                "__typename": "LoggedChatInteraction",
+                "id": logged_interaction.interaction.id(),
+            }),
+            ChatLogEntry::Redacted(redacted_interaction) => json!({
+                "__typename": "RedactedChatInteraction",
+                "entryHash": redacted_interaction.entry_hash,
+                "interactionId": redacted_interaction.interaction_id,
+            }),
+        })
+        .collect::<Vec<_>>();
+    let expected_data =
+        async_graphql::Value::from_json(json!({ "chatLog": { "entries": expected_entries } }))
+            .unwrap();
+
+    assert!(response.errors.is_empty());
+    assert_eq!(response.data, expected_data);
+
+    let request = async_graphql::Request::new(format!(
+        "query {{ verifyChatInteraction(index: {index}, prompt: \"\", response: \"\") }}"
+    ));
+
+    let response = service.handle_query(request).blocking_wait();
+
+    let is_redacted = matches!(entries[index], ChatLogEntry::Redacted(_));
+    let redacted_error = format!("Chat interaction at {index} was redacted");
+
+    assert_eq!(
+        response
+            .errors
+            .iter()
+            .any(|error| error.message == redacted_error),
+        is_redacted
+    );
+}
+
+/// Tests if the chat log's entries that were not pruned are read by their indices.
+#[proptest]
+fn read_pruned_chat_log(
+    #[any(proptest::sample::size_range(1..10).lift())] entries: Vec<ChatLogEntry>,
+    #[strategy(0..=#entries.len())] pruned_count: usize,
+    #[strategy(0..#entries.len())] index: usize,
+    #[strategy(proptest::option::of(0..=#entries.len() + 2))] start: Option<usize>,
+    #[strategy(proptest::option::of(0..=#entries.len() + 2))] end: Option<usize>,
+) {
+    let service = setup_service_with_pruned_chat_log(&entries, pruned_count);
+
+    let range_arguments = [("start", start), ("end", end)]
+        .into_iter()
+        .filter_map(|(name, value)| value.map(|value| format!("{name}: {value}")))
+        .collect::<Vec<_>>();
+    let range_arguments = if range_arguments.is_empty() {
+        String::new()
+    } else {
+        format!("({})", range_arguments.join(", "))
+    };
+    let request = async_graphql::Request::new(format!(
+        "query {{ chatLog {{ \
+            count, \
+            entries {{ __typename }}, \
+            range: entries{range_arguments} {{ __typename }}, \
+            entry(index: {index}) {{ __typename }} \
+        }} }}"
+    ));
+
+    let response = service.handle_query(request).blocking_wait();
+
+    let typename = |entry: &ChatLogEntry| match entry {
+        ChatLogEntry::Logged(_) => "LoggedChatInteraction",
+        ChatLogEntry::Redacted(_) => "RedactedChatInteraction",
+    };
+    let expected_entries = entries[pruned_count..]
+        .iter()
+        .map(|entry| json!({ "__typename": typename(entry) }))
+        .collect::<Vec<_>>();
+    let range_start = start.unwrap_or(0).max(pruned_count);
+    let range_end = end.unwrap_or(entries.len()).min(entries.len());
+    let expected_range = entries
+        .get(range_start..range_end)
+        .unwrap_or_default()
+        .iter()
+        .map(|entry| json!({ "__typename": typename(entry) }))
+        .collect::<Vec<_>>();
+    let expected_entry =
+        (index >= pruned_count).then(|| json!({ "__typename": typename(&entries[index]) }));
+    let expected_data = async_graphql::Value::from_json(json!({
+        "chatLog": {
+            "count": entries.len() - pruned_count,
+            "entries": expected_entries,
+            "range": expected_range,
+            "entry": expected_entry,
+        }
+    }))
+    .unwrap();
+
+    assert!(response.errors.is_empty());
+    assert_eq!(response.data, expected_data);
+
+    let request = async_graphql::Request::new(format!(
+        "query {{ verifyChatInteraction(index: {index}, prompt: \"\", response: \"\") }}"
+    ));
+
+    let response = service.handle_query(request).blocking_wait();
+
+    let pruned_error = format!("Chat interaction at {index} was pruned");
+
+    assert_eq!(
+        response
+            .errors
+            .iter()
+            .any(|error| error.message == pruned_error),
+        index < pruned_count
+    );
+}
+
+/// Tests if plaintexts can be verified against the logged chat interactions.
+#[proptest]
+fn verify_chat_interaction(
+    interactions: Vec<LoggedChatInteraction>,
+    #[strategy(0..#interactions.len().max(1))] index: usize,
+    #[strategy("[A-Za-z0-9., ]*")] plaintext_prompt: String,
+    #[strategy("[A-Za-z0-9., ]*")] plaintext_response: String,
+    privacy: PrivacyMode,
+    prompt_salt: CryptoHash,
+    response_salt: CryptoHash,
+    other_salt: CryptoHash,
+    entry: LoggedChatInteraction,
+) {
+    prop_assume!(other_salt != prompt_salt && other_salt != response_salt);
+
+    let mut interactions = interactions;
+    let logged_interaction = LoggedChatInteraction {
+        interaction: ChatInteraction {
+            model: DEFAULT_CHAT_MODEL.to_owned(),
+            prompt: protect_content(&plaintext_prompt, privacy, prompt_salt),
+            response: protect_content(&plaintext_response, privacy, response_salt),
+            tool_calls: vec![],
+            choice_index: 0,
+        },
+        ..entry
+    };
+
+    if index < interactions.len() {
+        interactions[index] = logged_interaction;
+    } else {
+        interactions.push(logged_interaction);
+    }
+
+    let service = setup_service_with_chat_log(&interactions);
+
+    let query_verification = |prompt: &str, response: &str, salts: Option<[CryptoHash; 2]>| {
+        let salt_arguments = salts
+            .map(|[prompt_salt, response_salt]| {
+                format!(", promptSalt: \"{prompt_salt}\", responseSalt: \"{response_salt}\"")
+            })
+            .unwrap_or_default();
+        let request = async_graphql::Request::new(format!(
+            "query {{ verifyChatInteraction(\
+                index: {index}, \
+                prompt: {prompt:?}, \
+                response: {response:?}{salt_arguments}\
+            ) }}"
+        ));
+
+        let response = service.handle_query(request).blocking_wait();
+
+        let async_graphql::Value::Object(ref response_data) = response.data else {
+            panic!("Unexpected response data type");
+        };
+        let async_graphql::Value::Boolean(is_verified) = response_data["verifyChatInteraction"]
+        else {
+            panic!("Unexpected verification result type");
+        };
+
+        is_verified
+    };
+
+    let salts = Some([prompt_salt, response_salt]);
+    let is_public = privacy == PrivacyMode::Public;
+
+    assert!(query_verification(
+        &plaintext_prompt,
+        &plaintext_response,
+        salts
+    ));
+    assert!(!query_verification(
+        &format!("{plaintext_prompt}!"),
+        &plaintext_response,
+        salts
+    ));
+    assert!(!query_verification(
+        &plaintext_prompt,
+        &format!("{plaintext_response}!"),
+        salts
+    ));
+    assert_eq!(
+        query_verification(&plaintext_prompt, &plaintext_response, None),
+        is_public
+    );
+    assert_eq!(
+        query_verification(
+            &plaintext_prompt,
+            &plaintext_response,
+            Some([other_salt, response_salt])
+        ),
+        is_public
+    );
+    assert_eq!(
+        query_verification(
+            &plaintext_prompt,
+            &plaintext_response,
+            Some([prompt_salt, other_salt])
+        ),
+        is_public
+    );
+}
+
+/// Tests if the logged chat interactions can be listed by the user that requested them.
+#[proptest]
+fn list_chat_interactions_by_requester(
+    interactions: Vec<LoggedChatInteraction>,
+    requester: AccountOwner,
+    #[strategy(proptest::collection::vec(any::<bool>(), #interactions.len()))]
+    requested_by_user: Vec<bool>,
+) {
+    let mut interactions = interactions;
+    for (interaction, is_requested_by_user) in interactions.iter_mut().zip(requested_by_user) {
+        if is_requested_by_user {
+            interaction.requester = Some(requester);
+        }
+    }
+
+    let service = setup_service_with_chat_log(&interactions);
+
+    let request = async_graphql::Request::new(format!(
+        "query {{ chatInteractionsByRequester(requester: \"{requester}\") {{ id }} }}"
+    ));
+
+    let response = service.handle_query(request).blocking_wait();
+
+    let expected_ids = interactions
+        .iter()
+        .filter(|entry| entry.requester == Some(requester))
+        .map(|entry| json!({ "id": entry.interaction.id() }))
+        .collect::<Vec<_>>();
+    let expected_response = async_graphql::Response::new(
+        async_graphql::Value::from_json(json!({ "chatInteractionsByRequester": expected_ids }))
+            .unwrap(),
+    );
+
+    assert_eq!(response, expected_response);
+}
+
+/// Tests if the logged chat interactions can be filtered by when they were requested.
+#[proptest]
+fn list_chat_interactions_in_time_range(
+    interactions: Vec<LoggedChatInteraction>,
+    since: Option<u64>,
+    until: Option<u64>,
+) {
+    let service = setup_service_with_chat_log(&interactions);
+
+    let since_argument = since.map(|since| format!("since: {since}"));
+    let until_argument = until.map(|until| format!("until: {until}"));
+    let arguments = since_argument
+        .into_iter()
+        .chain(until_argument)
+        .collect::<Vec<_>>();
+    let arguments = if arguments.is_empty() {
+        String::new()
+    } else {
+        format!("({})", arguments.join(", "))
+    };
+    let request =
+        async_graphql::Request::new(format!("query {{ chatInteractions{arguments} {{ id }} }}"));
+
+    let response = service.handle_query(request).blocking_wait();
+
+    let expected_ids = interactions
+        .iter()
+        .filter(|entry| {
+            let requested_at = entry.requested_at.timestamp.micros();
+            since.is_none_or(|since| requested_at >= since)
+                && until.is_none_or(|until| requested_at < until)
+        })
+        .map(|entry| json!({ "id": entry.interaction.id() }))
+        .collect::<Vec<_>>();
+    let expected_response = async_graphql::Response::new(
+        async_graphql::Value::from_json(json!({ "chatInteractions": expected_ids })).unwrap(),
+    );
+
+    assert_eq!(response, expected_response);
+}
+
+/// Tests if the inclusion of a logged chat interaction can be proven against the chat log's
+/// Merkle root.
+#[proptest]
+fn prove_chat_log_inclusion(
+    #[any(proptest::sample::size_range(1..40).lift())] interactions: Vec<LoggedChatInteraction>,
+    #[strategy(0..#interactions.len())] index: usize,
+    tampered_interaction: LoggedChatInteraction,
+) {
+    let service = setup_service_with_chat_log(&interactions);
+
+    let request = async_graphql::Request::new(format!(
+        "query {{ \
+            chatLogMerkleRoot, \
+            chatLogInclusionProof(index: {index}) {{ index, logLength, siblings, peaks }} \
+        }}"
+    ));
+
+    let response = service.handle_query(request).blocking_wait();
+
+    let async_graphql::Value::Object(response_data) = response.data else {
+        panic!("Unexpected response data type");
+    };
+    let async_graphql::Value::Object(ref proof) = response_data["chatLogInclusionProof"] else {
+        panic!("Unexpected inclusion proof type");
+    };
+
+    let root = parse_scalar(&response_data["chatLogMerkleRoot"]);
+    let proof = ChatLogInclusionProof {
+        index: parse_scalar(&proof["index"]),
+        log_length: parse_scalar(&proof["logLength"]),
+        siblings: parse_scalar(&proof["siblings"]),
+        peaks: parse_scalar(&proof["peaks"]),
+    };
+
+    assert_eq!(proof.log_length, interactions.len() as u64);
+    assert!(proof.verify(&interactions[index], root));
+
+    if tampered_interaction != interactions[index] {
+        assert!(!proof.verify(&tampered_interaction, root));
+    }
+}
+
 /// Tests if the set of active Atoma nodes stored on chain can be inspected with GraphQL.
 #[proptest]
 fn read_active_atoma_nodes(nodes: HashSet<PublicKey>) {
@@ -110,31 +497,192 @@ fn read_active_atoma_nodes(nodes: HashSet<PublicKey>) {
 
     let persisted_nodes = active_nodes
         .iter()
-        .map(|node_value| {
-            let async_graphql::Value::List(byte_list) = node_value else {
-                panic!("Unexpected node entry type");
-            };
+        .map(parse_scalar::<PublicKey>)
+        .collect::<HashSet<_>>();
 
-            let bytes = byte_list
-                .iter()
-                .map(|byte_value| {
-                    let async_graphql::Value::Number(byte_number) = byte_value else {
-                        panic!("Unexpected node key byte type");
-                    };
-                    let byte = byte_number.as_u64().expect("Invalid value for a byte");
+    assert_eq!(persisted_nodes, nodes);
+}
 
-                    u8::try_from(byte).expect("Invalid integer for a byte")
-                })
-                .collect::<Vec<u8>>();
+/// Tests if the GraphQL representation of Atoma node keys includes their signature scheme.
+#[proptest]
+fn node_keys_include_their_scheme(node: PublicKey) {
+    let service = setup_service_with_nodes(
+        Timestamp::from(0),
+        &[(node, NodeStats::default(), true, false)],
+    );
 
-            let byte_array =
-                <[u8; 32]>::try_from(&*bytes).expect("Invalid number of bytes for a public key");
+    let request = async_graphql::Request::new("query { activeAtomaNodes }");
 
-            PublicKey::from(byte_array)
-        })
-        .collect::<HashSet<_>>();
+    let response = service.handle_query(request).blocking_wait();
 
-    assert_eq!(persisted_nodes, nodes);
+    let (scheme, bytes) = match node {
+        PublicKey::Ed25519(bytes) => ("Ed25519", bytes.to_vec()),
+        PublicKey::Secp256k1(bytes) => ("Secp256k1", bytes.to_vec()),
+        PublicKey::Secp256r1(bytes) => ("Secp256r1", bytes.to_vec()),
+    };
+    let expected_data = async_graphql::Value::from_json(json!({
+        "activeAtomaNodes": [{ scheme: bytes }],
+    }))
+    .unwrap();
+
+    assert!(response.errors.is_empty());
+    assert_eq!(response.data, expected_data);
+}
+
+/// Tests if the statistics of Atoma nodes stored on chain can be inspected with GraphQL.
+#[proptest]
+fn read_node_stats(node: PublicKey, #[strategy(node_stats())] stats: NodeStats) {
+    let service = setup_service_with_nodes(Timestamp::from(0), &[(node, stats, false, false)]);
+
+    let request = async_graphql::Request::new(
+        "query($node: PublicKey!) { nodeStats { entry(key: $node) { value { \
+            activeSince, \
+            claimedRewards, \
+            rejectedInteractions, \
+            reports, \
+            tokensServed, \
+            unclaimedRewards, \
+            verifiedInteractions \
+        } } } }",
+    )
+    .variables(async_graphql::Variables::from_json(json!({ "node": node })));
+
+    let response = service.handle_query(request).blocking_wait();
+
+    let expected_data = async_graphql::Value::from_json(json!({
+        "nodeStats": {
+            "entry": {
+                "value": {
+                    "activeSince": stats.active_since,
+                    "claimedRewards": stats.claimed_rewards,
+                    "rejectedInteractions": stats.rejected_interactions,
+                    "reports": stats.reports,
+                    "tokensServed": stats.tokens_served,
+                    "unclaimedRewards": stats.unclaimed_rewards,
+                    "verifiedInteractions": stats.verified_interactions,
+                },
+            },
+        },
+    }))
+    .unwrap();
+
+    assert!(response.errors.is_empty());
+    assert_eq!(response.data, expected_data);
+}
+
+/// Tests if the attestations of Atoma nodes stored on chain can be inspected with GraphQL.
+#[proptest]
+fn read_node_attestations(node: PublicKey, attestation: NodeAttestation) {
+    let runtime = ServiceRuntime::new();
+    let storage = runtime.key_value_store().to_mut();
+
+    let mut initial_state = Application::load(ViewStorageContext::new_unsafe(storage, vec![], ()))
+        .blocking_wait()
+        .expect("Failed to load state from mock storage");
+
+    initial_state
+        .attested_atoma_nodes
+        .insert(&node, attestation)
+        .expect("Failed to insert node attestation in initial state");
+
+    initial_state
+        .save()
+        .blocking_wait()
+        .expect("Failed to save initial state to mock storage");
+
+    let service = setup_service(runtime);
+
+    let request = async_graphql::Request::new(
+        "query($node: PublicKey!) { attestedAtomaNodes { entry(key: $node) { value { \
+            attestedAt { blockHeight, timestamp }, \
+            attester, \
+            enclave { measurement, platform } \
+        } } } }",
+    )
+    .variables(async_graphql::Variables::from_json(json!({ "node": node })));
+
+    let response = service.handle_query(request).blocking_wait();
+
+    let platform = match attestation.enclave.platform {
+        TeePlatform::IntelTdx => "INTEL_TDX",
+        TeePlatform::NvidiaCc => "NVIDIA_CC",
+    };
+    let expected_data = json!({
+        "attestedAtomaNodes": {
+            "entry": {
+                "value": {
+                    "attestedAt": {
+                        "blockHeight": attestation.attested_at.block_height,
+                        "timestamp": attestation.attested_at.timestamp,
+                    },
+                    "attester": attestation.attester,
+                    "enclave": {
+                        "measurement": attestation.enclave.measurement,
+                        "platform": platform,
+                    },
+                },
+            },
+        },
+    });
+
+    assert!(response.errors.is_empty());
+    assert_eq!(
+        response
+            .data
+            .into_json()
+            .expect("Response data should be representable as JSON"),
+        expected_data
+    );
+}
+
+/// Tests if the active Atoma nodes that aren't suspended are ranked by their reputation.
+#[proptest]
+fn rank_nodes_by_reputation(
+    #[strategy(proptest::collection::vec(
+        (any::<PublicKey>(), node_stats(), any::<bool>(), any::<bool>()),
+        0..10,
+    ))]
+    nodes: Vec<(PublicKey, NodeStats, bool, bool)>,
+    #[strategy(any::<u64>().prop_map(Timestamp::from))] now: Timestamp,
+) {
+    let service = setup_service_with_nodes(now, &nodes);
+
+    let request = async_graphql::Request::new(
+        "query { nodeRankings { node, reputation, stats { verifiedInteractions } } }",
+    );
+
+    let response = service.handle_query(request).blocking_wait();
+
+    let mut expected_rankings = nodes
+        .iter()
+        .map(|(node, stats, is_active, is_suspended)| (*node, (*stats, *is_active, *is_suspended)))
+        .collect::<BTreeMap<_, _>>()
+        .into_iter()
+        .filter(|(_, (_, is_active, is_suspended))| *is_active && !*is_suspended)
+        .map(|(node, (stats, _, _))| (node, stats.reputation(now), stats))
+        .collect::<Vec<_>>();
+    expected_rankings.sort_by(
+        |(left_node, left_reputation, _), (right_node, right_reputation, _)| {
+            right_reputation
+                .total_cmp(left_reputation)
+                .then(left_node.cmp(right_node))
+        },
+    );
+
+    let expected_data = async_graphql::Value::from_json(json!({
+        "nodeRankings": expected_rankings
+            .into_iter()
+            .map(|(node, reputation, stats)| json!({
+                "node": node,
+                "reputation": reputation,
+                "stats": { "verifiedInteractions": stats.verified_interactions },
+            }))
+            .collect::<Vec<_>>(),
+    }))
+    .unwrap();
+
+    assert!(response.errors.is_empty());
+    assert_eq!(response.data, expected_data);
 }
 
 /// Tests if `chat` mutations perform an HTTP request to the Atoma proxy, and generates the
@@ -142,11 +690,14 @@ fn read_active_atoma_nodes(nodes: HashSet<PublicKey>) {
 #[proptest]
 fn performs_http_query(
     #[strategy("[A-Za-z0-9%=]*")] api_token: String,
-    interaction: ChatInteraction,
+    #[strategy("[A-Za-z0-9., ]*")] prompt: String,
+    #[strategy("[A-Za-z0-9., ]*")] response: String,
+    prompt_tokens: u32,
+    completion_tokens: u32,
 ) {
     let mut service = setup_service(ServiceRuntime::new());
+    let total_tokens = u64::from(prompt_tokens) + u64::from(completion_tokens);
 
-    let prompt = &interaction.prompt;
     let request = async_graphql::Request::new(format!(
         "mutation {{ \
             chat(\
@@ -155,7 +706,13 @@ fn performs_http_query(
                     content: {prompt:?}, \
                     role: \"user\"
                 }}\
-            ) \
+            ) {{ \
+                response, \
+                model, \
+                usage {{ promptTokens, completionTokens, totalTokens }}, \
+                interactionId, \
+                operation \
+            }} \
         }}"
     ));
 
@@ -174,13 +731,17 @@ fn performs_http_query(
             \"choices\": [\
                 {{
                      \"message\": {{\
-                         \"content\": {:?},
+                         \"content\": {response:?},
                          \"role\": \"\"
                     }}\
                 }}\
-            ] \
-        }}",
-        interaction.response
+            ], \
+            \"usage\": {{ \
+                \"prompt_tokens\": {prompt_tokens}, \
+                \"completion_tokens\": {completion_tokens}, \
+                \"total_tokens\": {total_tokens} \
+            }} \
+        }}"
     );
 
     Arc::get_mut(&mut service.runtime)
@@ -195,19 +756,1557 @@ fn performs_http_query(
             http::Response::ok(mock_response),
         );
 
-    let response = service.handle_query(request).blocking_wait();
+    let interaction = ChatInteraction {
+        model: DEFAULT_CHAT_MODEL.to_owned(),
+        prompt: ChatContent::Public(prompt),
+        response: ChatContent::Public(response.clone()),
+        tool_calls: vec![],
+        choice_index: 0,
+    };
+
+    let graphql_response = service.handle_query(request).blocking_wait();
 
-    let expected_operation = Operation::LogChatInteraction { interaction };
-    let expected_bytes =
-        bcs::to_bytes(&expected_operation).expect("`Operation` should be serializable");
+    let mut expected_result = expected_chat_result(&response, interaction);
+    expected_result["usage"] = json!({
+        "promptTokens": prompt_tokens,
+        "completionTokens": completion_tokens,
+        "totalTokens": total_tokens,
+    });
     let expected_response = async_graphql::Response::new(
-        async_graphql::Value::from_json(json!({"chat": expected_bytes})).unwrap(),
+        async_graphql::Value::from_json(json!({ "chat": expected_result })).unwrap(),
     );
 
-    assert_eq!(response, expected_response);
+    assert_eq!(graphql_response, expected_response);
 }
 
-/// Creates a [`ApplicationService`] instance to be tested.
-fn setup_service(runtime: ServiceRuntime<ApplicationService>) -> ApplicationService {
-    ApplicationService::new(runtime).blocking_wait()
+/// Tests if node receipts signed for a logged record are attached to the operation that logs
+/// it, so that the contract accepts them, and if receipts signed for other records are rejected.
+#[proptest]
+fn attaches_node_receipts_to_operations(
+    interaction: ChatInteraction,
+    #[any(proptest::sample::size_range(1..4).lift())] node_secrets: Vec<[u8; 32]>,
+    request_id: CryptoHash,
+    #[strategy(0..=i64::MAX as u64)] tokens: u64,
+    quorum: u32,
+    other_record_id: CryptoHash,
+) {
+    prop_assume!(other_record_id != interaction.id());
+
+    let service = setup_service(ServiceRuntime::new());
+
+    let operation = Operation::LogChatInteraction {
+        interaction: interaction.clone(),
+        payment: Amount::ZERO,
+        receipts: vec![],
+        quorum: 0,
+    };
+    let sign_receipts = |record_id: CryptoHash| {
+        node_secrets
+            .iter()
+            .map(|secret| {
+                let signing_key = SigningKey::from_bytes(secret);
+                let response = NodeResponse {
+                    request_id,
+                    choice_index: interaction.choice_index,
+                    interaction_id: record_id,
+                    tokens,
+                };
+
+                NodeReceipt {
+                    node: PublicKey::from(signing_key.verifying_key().to_bytes()),
+                    request_id,
+                    tokens,
+                    signature: Signature::from(
+                        signing_key
+                            .sign(CryptoHash::new(&response).as_bytes().as_slice())
+                            .to_bytes(),
+                    ),
+                }
+            })
+            .collect::<Vec<_>>()
+    };
+    let attach_receipts = |receipts: &[NodeReceipt]| {
+        let receipts_json = receipts
+            .iter()
+            .map(|receipt| {
+                json!({
+                    "node": receipt.node,
+                    "requestId": receipt.request_id,
+                    "tokens": receipt.tokens,
+                    "signature": receipt.signature,
+                })
+            })
+            .collect::<Vec<_>>();
+        let request = async_graphql::Request::new(
+            "mutation($operation: String!, $receipts: [NodeReceipt!]!, $quorum: Int!) { \
+                attachNodeReceipts(operation: $operation, receipts: $receipts, quorum: $quorum) \
+            }",
+        )
+        .variables(async_graphql::Variables::from_json(json!({
+            "operation": BASE64
+                .encode(bcs::to_bytes(&operation).expect("`Operation` should be serializable")),
+            "receipts": receipts_json,
+            "quorum": quorum,
+        })));
+
+        service.handle_query(request).blocking_wait()
+    };
+
+    let receipts = sign_receipts(interaction.id());
+    let response = attach_receipts(&receipts);
+
+    assert!(response.errors.is_empty());
+
+    let async_graphql::Value::Object(response_data) = response.data else {
+        panic!("Unexpected response data type");
+    };
+    let attached_operation = bcs::from_bytes::<Operation>(
+        &BASE64
+            .decode(parse_scalar::<String>(&response_data["attachNodeReceipts"]))
+            .expect("Operation should be encoded in base64"),
+    )
+    .expect("Operation should be deserializable");
+
+    let Operation::LogChatInteraction {
+        interaction: attached_interaction,
+        receipts: attached_receipts,
+        quorum: attached_quorum,
+        ..
+    } = attached_operation
+    else {
+        panic!("Attaching receipts should not change the kind of operation");
+    };
+
+    assert_eq!(attached_interaction, interaction);
+    assert_eq!(attached_receipts, receipts);
+    assert_eq!(attached_quorum, quorum);
+    assert!(attached_receipts.iter().all(
+        |receipt| receipt.verify(attached_interaction.id(), attached_interaction.choice_index)
+    ));
+
+    let response = attach_receipts(&sign_receipts(other_record_id));
+
+    assert_eq!(response.errors.len(), 1);
+}
+
+/// Tests if `chat` mutations send the provided tools to the Atoma proxy, and log the tool calls
+/// requested in the response.
+#[proptest]
+fn performs_tool_calling_query(
+    #[strategy("[A-Za-z0-9., ]*")] prompt: String,
+    #[strategy("[A-Za-z0-9_]+")] function_name: String,
+    #[strategy("[A-Za-z0-9_-]+")] call_id: String,
+    #[strategy("[a-z]+")] argument: String,
+) {
+    let mut service = setup_service(ServiceRuntime::new());
+
+    let request = async_graphql::Request::new(format!(
+        "mutation {{ \
+            chat(\
+                apiToken: \"token\", \
+                message: {{ content: {prompt:?}, role: \"user\" }}, \
+                tools: [{{ \
+                    name: {function_name:?}, \
+                    description: \"Looks something up\", \
+                    parameters: {{ type: \"object\" }} \
+                }}]\
+            ) {{ \
+                response, \
+                model, \
+                usage {{ promptTokens, completionTokens, totalTokens }}, \
+                interactionId, \
+                operation \
+            }} \
+        }}"
+    ));
+
+    let arguments = json!({ "query": argument }).to_string();
+    let mock_response = json!({
+        "choices": [{
+            "message": {
+                "content": null,
+                "role": "assistant",
+                "tool_calls": [{
+                    "id": call_id,
+                    "type": "function",
+                    "function": { "name": function_name, "arguments": arguments },
+                }],
+            },
+        }],
+    });
+
+    Arc::get_mut(&mut service.runtime)
+        .expect("`ServiceRuntime` should not be shared before configuring expected HTTP requests")
+        .add_expected_http_request(
+            http::Request::post(
+                format!("{ATOMA_CLOUD_URL}/v1/chat/completions"),
+                format!(
+                    "{{\
+                        \"stream\":false,\
+                        \"messages\":[{{\"content\":{prompt:?},\"role\":\"user\"}}],\
+                        \"model\":\"meta-llama/Llama-3.3-70B-Instruct\",\
+                        \"max_tokens\":128,\
+                        \"tools\":[{{\
+                            \"type\":\"function\",\
+                            \"function\":{{\
+                                \"name\":{function_name:?},\
+                                \"description\":\"Looks something up\",\
+                                \"parameters\":{{\"type\":\"object\"}}\
+                            }}\
+                        }}]\
+                    }}"
+                ),
+            )
+            .with_header("Content-Type", b"application/json")
+            .with_header("Authorization", b"Bearer token"),
+            http::Response::ok(mock_response.to_string()),
+        );
+
+    let response = service.handle_query(request).blocking_wait();
+
+    let interaction = ChatInteraction {
+        model: DEFAULT_CHAT_MODEL.to_owned(),
+        prompt: ChatContent::Public(prompt),
+        response: ChatContent::Public(String::new()),
+        tool_calls: vec![ToolCall {
+            id: call_id,
+            name: function_name,
+            arguments: ChatContent::Public(arguments),
+        }],
+        choice_index: 0,
+    };
+    let expected_response = async_graphql::Response::new(
+        async_graphql::Value::from_json(json!({ "chat": expected_chat_result("", interaction) }))
+            .unwrap(),
+    );
+
+    assert_eq!(response, expected_response);
+}
+
+/// Tests if `chatChoices` mutations request multiple responses from the Atoma proxy, and
+/// generate an operation to log each of them.
+#[proptest]
+fn performs_multiple_choice_query(
+    #[strategy("[A-Za-z0-9., ]*")] prompt: String,
+    #[strategy(proptest::collection::vec("[A-Za-z0-9., ]*", 1..5))] responses: Vec<String>,
+) {
+    let mut service = setup_service(ServiceRuntime::new());
+    let choice_count = responses.len();
+
+    let request = async_graphql::Request::new(format!(
+        "mutation {{ \
+            chatChoices(\
+                apiToken: \"token\", \
+                message: {{ content: {prompt:?}, role: \"user\" }}, \
+                choiceCount: {choice_count}\
+            ) {{ index, response, operation }} \
+        }}"
+    ));
+
+    let mock_response = json!({
+        "choices": responses
+            .iter()
+            .enumerate()
+            .map(|(index, response)| json!({
+                "index": index,
+                "message": { "content": response, "role": "assistant" },
+            }))
+            .collect::<Vec<_>>(),
+    });
+
+    Arc::get_mut(&mut service.runtime)
+        .expect("`ServiceRuntime` should not be shared before configuring expected HTTP requests")
+        .add_expected_http_request(
+            http::Request::post(
+                format!("{ATOMA_CLOUD_URL}/v1/chat/completions"),
+                format!(
+                    "{{\
+                        \"stream\":false,\
+                        \"messages\":[{{\"content\":{prompt:?},\"role\":\"user\"}}],\
+                        \"model\":\"meta-llama/Llama-3.3-70B-Instruct\",\
+                        \"max_tokens\":128,\
+                        \"n\":{choice_count}\
+                    }}"
+                ),
+            )
+            .with_header("Content-Type", b"application/json")
+            .with_header("Authorization", b"Bearer token"),
+            http::Response::ok(mock_response.to_string()),
+        );
+
+    let response = service.handle_query(request).blocking_wait();
+
+    let expected_choices = responses
+        .into_iter()
+        .enumerate()
+        .map(|(index, response)| {
+            let expected_operation = Operation::LogChatInteraction {
+                interaction: ChatInteraction {
+                    model: DEFAULT_CHAT_MODEL.to_owned(),
+                    prompt: ChatContent::Public(prompt.clone()),
+                    response: ChatContent::Public(response.clone()),
+                    tool_calls: vec![],
+                    choice_index: index as u32,
+                },
+                payment: Amount::ZERO,
+                receipts: vec![],
+                quorum: 0,
+            };
+
+            json!({
+                "index": index,
+                "response": response,
+                "operation": BASE64.encode(
+                    bcs::to_bytes(&expected_operation).expect("`Operation` should be serializable"),
+                ),
+            })
+        })
+        .collect::<Vec<_>>();
+    let expected_response = async_graphql::Response::new(
+        async_graphql::Value::from_json(json!({ "chatChoices": expected_choices })).unwrap(),
+    );
+
+    assert_eq!(response, expected_response);
+}
+
+/// Tests if `chat` mutations generate the operation to log the selected choice when multiple
+/// responses are requested.
+#[proptest]
+fn logs_selected_choice(
+    #[strategy("[A-Za-z0-9., ]*")] prompt: String,
+    #[strategy(proptest::collection::vec("[A-Za-z0-9., ]*", 1..5))] responses: Vec<String>,
+    #[strategy(0..#responses.len())] choice: usize,
+) {
+    let mut service = setup_service(ServiceRuntime::new());
+    let choice_count = responses.len();
+
+    let request = async_graphql::Request::new(format!(
+        "mutation {{ \
+            chat(\
+                apiToken: \"token\", \
+                message: {{ content: {prompt:?}, role: \"user\" }}, \
+                choiceCount: {choice_count}, \
+                choice: {choice}\
+            ) {{ \
+                response, \
+                model, \
+                usage {{ promptTokens, completionTokens, totalTokens }}, \
+                interactionId, \
+                operation \
+            }} \
+        }}"
+    ));
+
+    let mock_response = json!({
+        "choices": responses
+            .iter()
+            .map(|response| json!({ "message": { "content": response, "role": "assistant" } }))
+            .collect::<Vec<_>>(),
+    });
+
+    Arc::get_mut(&mut service.runtime)
+        .expect("`ServiceRuntime` should not be shared before configuring expected HTTP requests")
+        .add_expected_http_request(
+            http::Request::post(
+                format!("{ATOMA_CLOUD_URL}/v1/chat/completions"),
+                format!(
+                    "{{\
+                        \"stream\":false,\
+                        \"messages\":[{{\"content\":{prompt:?},\"role\":\"user\"}}],\
+                        \"model\":\"meta-llama/Llama-3.3-70B-Instruct\",\
+                        \"max_tokens\":128,\
+                        \"n\":{choice_count}\
+                    }}"
+                ),
+            )
+            .with_header("Content-Type", b"application/json")
+            .with_header("Authorization", b"Bearer token"),
+            http::Response::ok(mock_response.to_string()),
+        );
+
+    let response = service.handle_query(request).blocking_wait();
+
+    let interaction = ChatInteraction {
+        model: DEFAULT_CHAT_MODEL.to_owned(),
+        prompt: ChatContent::Public(prompt),
+        response: ChatContent::Public(responses[choice].clone()),
+        tool_calls: vec![],
+        choice_index: choice as u32,
+    };
+    let expected_result = expected_chat_result(&responses[choice], interaction);
+    let expected_response = async_graphql::Response::new(
+        async_graphql::Value::from_json(json!({ "chat": expected_result })).unwrap(),
+    );
+
+    assert_eq!(response, expected_response);
+}
+
+/// Tests if `submitChat` mutations schedule the operation to log the chat interaction, and
+/// return the interaction's ID.
+#[proptest]
+fn schedules_chat_interaction(
+    #[strategy("[A-Za-z0-9., ]*")] prompt: String,
+    #[strategy("[A-Za-z0-9., ]*")] response: String,
+) {
+    let mut service = setup_service(ServiceRuntime::new());
+
+    let request = async_graphql::Request::new(format!(
+        "mutation {{ \
+            submitChat(\
+                apiToken: \"token\", \
+                message: {{ content: {prompt:?}, role: \"user\" }}\
+            ) \
+        }}"
+    ));
+
+    let mock_response = json!({ "choices": [{ "message": { "content": response, "role": "" } }] });
+
+    Arc::get_mut(&mut service.runtime)
+        .expect("`ServiceRuntime` should not be shared before configuring expected HTTP requests")
+        .add_expected_http_request(
+            http::Request::post(
+                format!("{ATOMA_CLOUD_URL}/v1/chat/completions"),
+                format!(
+                    "{{\
+                        \"stream\":false,\
+                        \"messages\":[{{\"content\":{prompt:?},\"role\":\"user\"}}],\
+                        \"model\":\"meta-llama/Llama-3.3-70B-Instruct\",\
+                        \"max_tokens\":128\
+                    }}"
+                ),
+            )
+            .with_header("Content-Type", b"application/json")
+            .with_header("Authorization", b"Bearer token"),
+            http::Response::ok(mock_response.to_string()),
+        );
+
+    let graphql_response = service.handle_query(request).blocking_wait();
+
+    let interaction = ChatInteraction {
+        model: DEFAULT_CHAT_MODEL.to_owned(),
+        prompt: ChatContent::Public(prompt),
+        response: ChatContent::Public(response),
+        tool_calls: vec![],
+        choice_index: 0,
+    };
+    let expected_response = async_graphql::Response::new(
+        async_graphql::Value::from_json(json!({ "submitChat": interaction.id() })).unwrap(),
+    );
+
+    assert_eq!(graphql_response, expected_response);
+    assert_eq!(
+        service.runtime.scheduled_operations::<Operation>(),
+        vec![Operation::LogChatInteraction {
+            interaction,
+            payment: Amount::ZERO,
+            receipts: vec![],
+            quorum: 0,
+        }]
+    );
+}
+
+/// Tests if `preview` queries perform a chat completion and return the unverified response
+/// without producing an operation.
+#[proptest]
+fn previews_chat_completion(
+    #[strategy("[A-Za-z0-9., ]*")] prompt: String,
+    #[strategy("[A-Za-z0-9., ]*")] response: String,
+) {
+    let mut service = setup_service(ServiceRuntime::new());
+
+    let request = async_graphql::Request::new(format!(
+        "query {{ \
+            preview(\
+                apiToken: \"token\", \
+                message: {{ content: {prompt:?}, role: \"user\" }}\
+            ) {{ response, model, usage {{ totalTokens }}, toolCalls {{ id }}, isVerified }} \
+        }}"
+    ));
+
+    let mock_response = json!({ "choices": [{ "message": { "content": response, "role": "" } }] });
+
+    Arc::get_mut(&mut service.runtime)
+        .expect("`ServiceRuntime` should not be shared before configuring expected HTTP requests")
+        .add_expected_http_request(
+            http::Request::post(
+                format!("{ATOMA_CLOUD_URL}/v1/chat/completions"),
+                format!(
+                    "{{\
+                        \"stream\":false,\
+                        \"messages\":[{{\"content\":{prompt:?},\"role\":\"user\"}}],\
+                        \"model\":\"meta-llama/Llama-3.3-70B-Instruct\",\
+                        \"max_tokens\":128\
+                    }}"
+                ),
+            )
+            .with_header("Content-Type", b"application/json")
+            .with_header("Authorization", b"Bearer token"),
+            http::Response::ok(mock_response.to_string()),
+        );
+
+    let graphql_response = service.handle_query(request).blocking_wait();
+
+    let expected_response = async_graphql::Response::new(
+        async_graphql::Value::from_json(json!({
+            "preview": {
+                "response": response,
+                "model": DEFAULT_CHAT_MODEL,
+                "usage": null,
+                "toolCalls": [],
+                "isVerified": false,
+            },
+        }))
+        .unwrap(),
+    );
+
+    assert_eq!(graphql_response, expected_response);
+    assert!(service.runtime.raw_scheduled_operations().is_empty());
+}
+
+/// Tests if requests to the Atoma proxy can use the API tokens stored on chain.
+#[proptest]
+fn uses_stored_api_tokens(
+    #[strategy(proptest::collection::btree_map("[a-z0-9-]+", "[A-Za-z0-9%=]+", 1..5))]
+    stored_tokens: BTreeMap<String, String>,
+    #[strategy(0..#stored_tokens.len())] token_index: usize,
+    api_token_secret: [u8; 32],
+    #[strategy("[A-Za-z0-9., ]*")] prompt: String,
+) {
+    let (name, token) = stored_tokens
+        .iter()
+        .nth(token_index)
+        .map(|(name, token)| (name.clone(), token.clone()))
+        .expect("Token index should be in range");
+
+    let api_token_key = crypto_box::SecretKey::from_bytes(api_token_secret).public_key();
+    let api_tokens = stored_tokens
+        .into_iter()
+        .map(|(name, token)| seal_api_token(&api_token_key, name, &token))
+        .collect::<Vec<_>>();
+    let mut service = setup_service_with_api_tokens(&api_tokens);
+
+    let request = async_graphql::Request::new(format!(
+        "mutation {{ \
+            chat(\
+                apiTokenName: {name:?}, \
+                apiTokenKey: {api_token_secret:?}, \
+                message: {{ content: {prompt:?}, role: \"user\" }}\
+            ) {{ response }} \
+        }}"
+    ));
+
+    let mock_response = json!({ "choices": [{ "message": { "content": "", "role": "" } }] });
+
+    Arc::get_mut(&mut service.runtime)
+        .expect("`ServiceRuntime` should not be shared before configuring expected HTTP requests")
+        .add_expected_http_request(
+            http::Request::post(
+                format!("{ATOMA_CLOUD_URL}/v1/chat/completions"),
+                format!(
+                    "{{\
+                        \"stream\":false,\
+                        \"messages\":[{{\"content\":{prompt:?},\"role\":\"user\"}}],\
+                        \"model\":\"meta-llama/Llama-3.3-70B-Instruct\",\
+                        \"max_tokens\":128\
+                    }}"
+                ),
+            )
+            .with_header("Content-Type", b"application/json")
+            .with_header("Authorization", format!("Bearer {token}").as_bytes()),
+            http::Response::ok(mock_response.to_string()),
+        );
+
+    let response = service.handle_query(request).blocking_wait();
+
+    let expected_response = async_graphql::Response::new(
+        async_graphql::Value::from_json(json!({ "chat": { "response": "" } })).unwrap(),
+    );
+
+    assert_eq!(response, expected_response);
+}
+
+/// Tests if stored API tokens are only used when they are explicitly named and the key to unseal
+/// them is provided.
+#[proptest]
+fn stored_api_tokens_require_name_and_key(
+    #[strategy("[a-z0-9-]+")] name: String,
+    #[strategy("[A-Za-z0-9%=]+")] token: String,
+    api_token_secret: [u8; 32],
+    other_secret: [u8; 32],
+) {
+    prop_assume!(api_token_secret != other_secret);
+
+    let api_token_key = crypto_box::SecretKey::from_bytes(api_token_secret).public_key();
+    let service =
+        setup_service_with_api_tokens(&[seal_api_token(&api_token_key, name.clone(), &token)]);
+
+    let cases = [
+        (
+            String::new(),
+            "Provide either an API token or the name of a stored API token".to_owned(),
+        ),
+        (
+            format!("(apiTokenName: {name:?})"),
+            "Stored API tokens require the key to unseal them".to_owned(),
+        ),
+        (
+            format!("(apiTokenName: {name:?}, apiTokenKey: {other_secret:?})"),
+            format!(
+                "The API token key is not the key that the API token named {name:?} is sealed to"
+            ),
+        ),
+    ];
+
+    for (token_arguments, expected_error) in cases {
+        let request =
+            async_graphql::Request::new(format!("query {{ models{token_arguments} {{ id }} }}"));
+
+        let response = service.handle_query(request).blocking_wait();
+
+        assert_eq!(response.errors.len(), 1);
+        assert_eq!(response.errors[0].message, expected_error);
+    }
+}
+
+/// Tests if the key dedicated to a stored API token is rejected when it is also the secret of the
+/// key that the chat interaction is sealed to.
+#[proptest]
+fn api_token_keys_are_not_encryption_keys(
+    #[strategy("[a-z0-9-]+")] name: String,
+    #[strategy("[A-Za-z0-9%=]+")] token: String,
+    api_token_secret: [u8; 32],
+    encryption_seed: [u8; 32],
+) {
+    let api_token_key = crypto_box::SecretKey::from_bytes(api_token_secret).public_key();
+    let service =
+        setup_service_with_api_tokens(&[seal_api_token(&api_token_key, name.clone(), &token)]);
+    let encryption_key = api_token_key.to_bytes();
+
+    let request = async_graphql::Request::new(format!(
+        "mutation {{ \
+            chat(\
+                apiTokenName: {name:?}, \
+                apiTokenKey: {api_token_secret:?}, \
+                message: {{ content: \"Hello\", role: \"user\" }}, \
+                privacy: ENCRYPTED, \
+                encryptionKey: {encryption_key:?}, \
+                encryptionSeed: {encryption_seed:?}\
+            ) {{ operation }} \
+        }}"
+    ));
+
+    let response = service.handle_query(request).blocking_wait();
+
+    assert_eq!(response.errors.len(), 1);
+    assert_eq!(
+        response.errors[0].message,
+        "The API token key must not be the secret of the encryption key"
+    );
+}
+
+/// Tests if API tokens are removed from the error messages of failed requests to the Atoma
+/// proxy.
+#[proptest]
+fn redacts_api_tokens_from_errors(#[strategy("[A-Za-z0-9]{8,16}")] api_token: String) {
+    let mut service = setup_service(ServiceRuntime::new());
+
+    let request = async_graphql::Request::new(format!(
+        "query {{ models(apiToken: {api_token:?}) {{ id }} }}"
+    ));
+
+    Arc::get_mut(&mut service.runtime)
+        .expect("`ServiceRuntime` should not be shared before configuring expected HTTP requests")
+        .add_expected_http_request(
+            http::Request::get(format!("{ATOMA_CLOUD_URL}/v1/models"))
+                .with_header("Authorization", format!("Bearer {api_token}").as_bytes()),
+            http::Response::ok(format!("Invalid token: {api_token}")),
+        );
+
+    let response = service.handle_query(request).blocking_wait();
+
+    assert_eq!(response.errors.len(), 1);
+    assert!(!response.errors[0].message.contains(&api_token));
+    assert!(response.errors[0].message.contains("[redacted]"));
+}
+
+/// Tests if `chat` mutations request structured output from the Atoma proxy, and only log
+/// responses that match the response schema.
+#[proptest]
+fn performs_structured_output_query(
+    #[strategy("[A-Za-z0-9., ]*")] prompt: String,
+    #[strategy("[A-Za-z ]*")] answer: String,
+    confidence: u8,
+    matches_schema: bool,
+) {
+    let mut service = setup_service(ServiceRuntime::new());
+
+    let schema = json!({
+        "type": "object",
+        "properties": {
+            "answer": { "type": "string" },
+            "confidence": { "type": "integer" },
+        },
+        "required": ["answer", "confidence"],
+    });
+
+    let request = async_graphql::Request::new(format!(
+        "mutation {{ \
+            chat(\
+                apiToken: \"token\", \
+                message: {{ content: {prompt:?}, role: \"user\" }}, \
+                responseSchema: {{ \
+                    type: \"object\", \
+                    properties: {{ \
+                        answer: {{ type: \"string\" }}, \
+                        confidence: {{ type: \"integer\" }} \
+                    }}, \
+                    required: [\"answer\", \"confidence\"] \
+                }}\
+            ) {{ \
+                response, \
+                model, \
+                usage {{ promptTokens, completionTokens, totalTokens }}, \
+                interactionId, \
+                operation \
+            }} \
+        }}"
+    ));
+
+    let response_json = if matches_schema {
+        json!({ "answer": answer, "confidence": confidence })
+    } else {
+        json!({ "answer": confidence, "confidence": answer })
+    }
+    .to_string();
+    let mock_response = json!({
+        "choices": [{
+            "message": { "content": response_json, "role": "assistant" },
+        }],
+    });
+
+    Arc::get_mut(&mut service.runtime)
+        .expect("`ServiceRuntime` should not be shared before configuring expected HTTP requests")
+        .add_expected_http_request(
+            http::Request::post(
+                format!("{ATOMA_CLOUD_URL}/v1/chat/completions"),
+                format!(
+                    "{{\
+                        \"stream\":false,\
+                        \"messages\":[{{\"content\":{prompt:?},\"role\":\"user\"}}],\
+                        \"model\":\"meta-llama/Llama-3.3-70B-Instruct\",\
+                        \"max_tokens\":128,\
+                        \"response_format\":{{\
+                            \"type\":\"json_schema\",\
+                            \"json_schema\":{{\"name\":\"response\",\"schema\":{schema}}}\
+                        }}\
+                    }}"
+                ),
+            )
+            .with_header("Content-Type", b"application/json")
+            .with_header("Authorization", b"Bearer token"),
+            http::Response::ok(mock_response.to_string()),
+        );
+
+    let response = service.handle_query(request).blocking_wait();
+
+    if matches_schema {
+        let interaction = ChatInteraction {
+            model: DEFAULT_CHAT_MODEL.to_owned(),
+            prompt: ChatContent::Public(prompt),
+            response: ChatContent::Public(response_json.clone()),
+            tool_calls: vec![],
+            choice_index: 0,
+        };
+        let expected_result = expected_chat_result(&response_json, interaction);
+        let expected_response = async_graphql::Response::new(
+            async_graphql::Value::from_json(json!({ "chat": expected_result })).unwrap(),
+        );
+
+        assert_eq!(response, expected_response);
+    } else {
+        assert_eq!(response.data, async_graphql::Value::Null);
+        assert_eq!(response.errors.len(), 1);
+
+        let extensions = response.errors[0]
+            .extensions
+            .as_ref()
+            .expect("Schema mismatch errors should have extensions");
+
+        assert_eq!(
+            extensions.get("code"),
+            Some(&async_graphql::Value::from("RESPONSE_SCHEMA_MISMATCH"))
+        );
+    }
+}
+
+/// Tests if `chat` mutations seal the contents of encrypted chat interactions to the
+/// provided encryption key.
+#[proptest]
+fn seals_encrypted_chat_interactions(
+    encryption_secret: [u8; 32],
+    encryption_seed: [u8; 32],
+    #[strategy("[A-Za-z0-9., ]*")] prompt: String,
+    #[strategy("[A-Za-z0-9., ]*")] response: String,
+) {
+    let secret_key = crypto_box::SecretKey::from_bytes(encryption_secret);
+    let (interaction, [prompt_salt, response_salt]) =
+        seal_chat_interaction(secret_key.public_key(), encryption_seed, &prompt, &response);
+
+    for (content, plaintext, salt) in [
+        (interaction.prompt, prompt, prompt_salt),
+        (interaction.response, response, response_salt),
+    ] {
+        let ChatContent::Encrypted {
+            ciphertext,
+            commitment,
+        } = content
+        else {
+            panic!("Chat interaction was not encrypted");
+        };
+
+        let decrypted = secret_key
+            .unseal(&ciphertext)
+            .expect("Failed to decrypt chat content");
+
+        assert_eq!(decrypted, plaintext.as_bytes());
+        assert_eq!(commitment, ChatContent::commit_salted(&plaintext, salt));
+        assert_ne!(commitment, ChatContent::commit(&plaintext));
+    }
+
+    assert_eq!(interaction.model, DEFAULT_CHAT_MODEL);
+}
+
+/// Tests if the keys used to seal encrypted chat interactions and the salts of their commitments
+/// depend on the client's secret seed, so that they can't be rebuilt from the data stored on
+/// chain.
+#[proptest]
+fn sealing_keys_are_not_derived_from_chain_data(
+    encryption_secret: [u8; 32],
+    encryption_seed: [u8; 32],
+    other_seed: [u8; 32],
+    #[strategy("[A-Za-z0-9., ]*")] prompt: String,
+    #[strategy("[A-Za-z0-9., ]*")] response: String,
+) {
+    prop_assume!(encryption_seed != other_seed);
+
+    let encryption_key = crypto_box::SecretKey::from_bytes(encryption_secret).public_key();
+    let (interaction, _) =
+        seal_chat_interaction(encryption_key.clone(), encryption_seed, &prompt, &response);
+    let (other_interaction, _) =
+        seal_chat_interaction(encryption_key, other_seed, &prompt, &response);
+
+    for (content, other_content) in [
+        (interaction.prompt, other_interaction.prompt),
+        (interaction.response, other_interaction.response),
+    ] {
+        let (
+            ChatContent::Encrypted {
+                ciphertext,
+                commitment,
+            },
+            ChatContent::Encrypted {
+                ciphertext: other_ciphertext,
+                commitment: other_commitment,
+            },
+        ) = (content, other_content)
+        else {
+            panic!("Chat interaction was not encrypted");
+        };
+
+        assert_ne!(commitment, other_commitment);
+        assert_ne!(ciphertext, other_ciphertext);
+    }
+}
+
+/// Tests if `chat` mutations reject hashed or encrypted chat interactions without a valid
+/// encryption seed.
+#[proptest]
+fn private_chat_interactions_require_seed(
+    #[strategy(prop_oneof![Just(PrivacyMode::Hashed), Just(PrivacyMode::Encrypted)])]
+    privacy: PrivacyMode,
+    encryption_secret: [u8; 32],
+    #[strategy(proptest::option::of(proptest::collection::vec(any::<u8>(), 0..64)))]
+    encryption_seed: Option<Vec<u8>>,
+) {
+    prop_assume!(encryption_seed
+        .as_ref()
+        .is_none_or(|seed| seed.len() != SEALING_SEED_SIZE));
+
+    let (privacy_argument, mode_name) = match privacy {
+        PrivacyMode::Hashed => ("HASHED", "Hashed"),
+        PrivacyMode::Encrypted => ("ENCRYPTED", "Encrypted"),
+        PrivacyMode::Public => unreachable!("Public chat interactions don't need a seed"),
+    };
+    let expected_error = match &encryption_seed {
+        None => format!("{mode_name} chat interactions require an encryption seed"),
+        Some(_) => format!("Encryption seed must have {SEALING_SEED_SIZE} bytes"),
+    };
+
+    let encryption_key = crypto_box::SecretKey::from_bytes(encryption_secret)
+        .public_key()
+        .to_bytes();
+    let seed_argument = encryption_seed
+        .map(|seed| format!(", encryptionSeed: {seed:?}"))
+        .unwrap_or_default();
+    let service = setup_service(ServiceRuntime::new());
+
+    let request = async_graphql::Request::new(format!(
+        "mutation {{ \
+            chat(\
+                apiToken: \"token\", \
+                message: {{ content: \"Hello\", role: \"user\" }}, \
+                privacy: {privacy_argument}, \
+                encryptionKey: {encryption_key:?}{seed_argument}\
+            ) {{ operation }} \
+        }}"
+    ));
+
+    let response = service.handle_query(request).blocking_wait();
+
+    assert_eq!(response.errors.len(), 1);
+    assert_eq!(response.errors[0].message, expected_error);
+}
+
+/// Tests if `embed` mutations perform an HTTP request to the Atoma proxy, and generate the
+/// operation to log the embedding.
+#[proptest]
+fn performs_embedding_query(
+    #[strategy("[A-Za-z0-9%=]*")] api_token: String,
+    #[strategy("[A-Za-z0-9., ]*")] input: String,
+    #[strategy(proptest::collection::vec(-1.0_f32..1.0, 1..32))] vector: Vec<f32>,
+) {
+    let mut service = setup_service(ServiceRuntime::new());
+
+    let request = async_graphql::Request::new(format!(
+        "mutation {{ \
+            embed(apiToken: \"{api_token}\", input: {input:?}) {{ vector, embeddingId, operation }} \
+        }}"
+    ));
+
+    let expected_body = format!("{{\"model\":\"{DEFAULT_EMBEDDING_MODEL}\",\"input\":{input:?}}}");
+    let mock_response = json!({
+        "object": "list",
+        "model": DEFAULT_EMBEDDING_MODEL,
+        "data": [{ "object": "embedding", "index": 0, "embedding": vector }],
+    });
+
+    Arc::get_mut(&mut service.runtime)
+        .expect("`ServiceRuntime` should not be shared before configuring expected HTTP requests")
+        .add_expected_http_request(
+            http::Request::post(format!("{ATOMA_CLOUD_URL}/v1/embeddings"), expected_body)
+                .with_header("Content-Type", b"application/json")
+                .with_header("Authorization", format!("Bearer {api_token}").as_bytes()),
+            http::Response::ok(mock_response.to_string()),
+        );
+
+    let response = service.handle_query(request).blocking_wait();
+
+    let expected_record = EmbeddingRecord {
+        input_hash: ChatContent::commit(&input),
+        model: DEFAULT_EMBEDDING_MODEL.to_owned(),
+        dimensions: vector.len() as u32,
+        vector: vector.clone(),
+    };
+    let expected_id = expected_record.id();
+    let expected_operation = Operation::LogEmbedding {
+        embedding: expected_record,
+        receipts: vec![],
+        quorum: 0,
+    };
+    let expected_operation = BASE64
+        .encode(bcs::to_bytes(&expected_operation).expect("`Operation` should be serializable"));
+    let expected_response = async_graphql::Response::new(
+        async_graphql::Value::from_json(json!({
+            "embed": {
+                "vector": vector,
+                "embeddingId": expected_id,
+                "operation": expected_operation,
+            },
+        }))
+        .unwrap(),
+    );
+
+    assert_eq!(response, expected_response);
+}
+
+/// Tests if `generateImage` mutations perform an HTTP request to the Atoma proxy, download
+/// images that are only provided as URLs, and generate the operation to log the image hashes.
+#[proptest]
+fn performs_image_generation_query(
+    #[strategy("[A-Za-z0-9%=]*")] api_token: String,
+    #[strategy("[A-Za-z0-9., ]*")] prompt: String,
+    #[any(proptest::sample::size_range(1..5).lift())] images: Vec<(Vec<u8>, bool)>,
+) {
+    let mut service = setup_service(ServiceRuntime::new());
+    let runtime = Arc::get_mut(&mut service.runtime)
+        .expect("`ServiceRuntime` should not be shared before configuring expected HTTP requests");
+
+    let request = async_graphql::Request::new(format!(
+        "mutation {{ \
+            generateImage(apiToken: \"{api_token}\", prompt: {prompt:?}) {{ \
+                images {{ contentHash, url, data }}, \
+                generationId, \
+                operation \
+            }} \
+        }}"
+    ));
+
+    let mut response_images = Vec::with_capacity(images.len());
+    let mut expected_downloads = Vec::new();
+    let mut expected_images = Vec::with_capacity(images.len());
+    let mut expected_records = Vec::with_capacity(images.len());
+
+    for (index, (bytes, is_downloaded)) in images.into_iter().enumerate() {
+        let content_hash = DataBlobHash(Blob::new_data(bytes.clone()).id().hash);
+        let encoded = BASE64.encode(&bytes);
+        let url = is_downloaded.then(|| format!("https://images.atoma.network/{index}"));
+
+        if let Some(url) = &url {
+            expected_downloads.push((http::Request::get(url), http::Response::ok(bytes)));
+            response_images.push(json!({ "url": url }));
+        } else {
+            response_images.push(json!({ "b64_json": encoded }));
+        }
+
+        expected_images.push(json!({ "contentHash": content_hash, "url": url, "data": encoded }));
+        expected_records.push(ImageRecord { content_hash, url });
+    }
+
+    let expected_body = format!(
+        "{{\
+            \"prompt\":{prompt:?},\
+            \"model\":\"{DEFAULT_IMAGE_MODEL}\",\
+            \"n\":1,\
+            \"size\":\"1024x1024\"\
+        }}"
+    );
+    let mock_response = json!({ "created": 0, "data": response_images });
+
+    runtime.add_expected_http_request(
+        http::Request::post(
+            format!("{ATOMA_CLOUD_URL}/v1/images/generations"),
+            expected_body,
+        )
+        .with_header("Content-Type", b"application/json")
+        .with_header("Authorization", format!("Bearer {api_token}").as_bytes()),
+        http::Response::ok(mock_response.to_string()),
+    );
+
+    for (request, response) in expected_downloads {
+        runtime.add_expected_http_request(request, response);
+    }
+
+    let response = service.handle_query(request).blocking_wait();
+
+    let expected_record = ImageGenerationRecord {
+        prompt,
+        model: DEFAULT_IMAGE_MODEL.to_owned(),
+        images: expected_records,
+    };
+    let expected_id = expected_record.id();
+    let expected_operation = Operation::LogImageGeneration {
+        generation: expected_record,
+        receipts: vec![],
+        quorum: 0,
+    };
+    let expected_operation = BASE64
+        .encode(bcs::to_bytes(&expected_operation).expect("`Operation` should be serializable"));
+    let expected_response = async_graphql::Response::new(
+        async_graphql::Value::from_json(json!({
+            "generateImage": {
+                "images": expected_images,
+                "generationId": expected_id,
+                "operation": expected_operation,
+            },
+        }))
+        .unwrap(),
+    );
+
+    assert_eq!(response, expected_response);
+}
+
+/// Tests if the `models` query lists the models available from the Atoma proxy.
+#[proptest]
+fn lists_available_models(
+    #[strategy("[A-Za-z0-9%=]*")] api_token: String,
+    #[strategy(proptest::collection::btree_set("[A-Za-z0-9./-]+", 0..10))] model_ids: BTreeSet<
+        String,
+    >,
+    #[strategy(proptest::collection::btree_set("[A-Za-z0-9./-]+", 0..5))] allowed_models: BTreeSet<
+        String,
+    >,
+    include_default_model: bool,
+) {
+    let runtime = ServiceRuntime::new();
+    let storage = runtime.key_value_store().to_mut();
+
+    let mut initial_state = Application::load(ViewStorageContext::new_unsafe(storage, vec![], ()))
+        .blocking_wait()
+        .expect("Failed to load state from mock storage");
+
+    for model in &allowed_models {
+        initial_state
+            .allowed_models
+            .insert(model)
+            .expect("Failed to add a model to the set of allowed models");
+    }
+
+    initial_state
+        .save()
+        .blocking_wait()
+        .expect("Failed to save initial state to mock storage");
+
+    let mut service = setup_service(runtime);
+
+    let mut model_ids = model_ids;
+    if include_default_model {
+        model_ids.insert(DEFAULT_CHAT_MODEL.to_owned());
+    }
+
+    let mock_response = json!({
+        "object": "list",
+        "data": model_ids
+            .iter()
+            .map(|id| json!({ "id": id, "object": "model", "created": 0, "owned_by": "atoma" }))
+            .collect::<Vec<_>>(),
+    });
+
+    Arc::get_mut(&mut service.runtime)
+        .expect("`ServiceRuntime` should not be shared before configuring expected HTTP requests")
+        .add_expected_http_request(
+            http::Request::get(format!("{ATOMA_CLOUD_URL}/v1/models"))
+                .with_header("Authorization", format!("Bearer {api_token}").as_bytes()),
+            http::Response::ok(mock_response.to_string()),
+        );
+
+    let request = async_graphql::Request::new(format!(
+        "query {{ \
+            models(apiToken: \"{api_token}\") {{ id, ownedBy, isDefault, isAvailable, isAllowed }} \
+        }}"
+    ));
+
+    let response = service.handle_query(request).blocking_wait();
+
+    let available_models = model_ids.iter().map(|id| {
+        json!({
+            "id": id,
+            "ownedBy": "atoma",
+            "isDefault": id == DEFAULT_CHAT_MODEL,
+            "isAvailable": true,
+            "isAllowed": allowed_models.contains(id),
+        })
+    });
+    let unavailable_models = allowed_models.difference(&model_ids).map(|id| {
+        json!({
+            "id": id,
+            "ownedBy": null,
+            "isDefault": id == DEFAULT_CHAT_MODEL,
+            "isAvailable": false,
+            "isAllowed": true,
+        })
+    });
+    let expected_models = available_models
+        .chain(unavailable_models)
+        .collect::<Vec<_>>();
+    let expected_response = async_graphql::Response::new(
+        async_graphql::Value::from_json(json!({ "models": expected_models })).unwrap(),
+    );
+
+    assert_eq!(response, expected_response);
+}
+
+/// Creates a [`ApplicationService`] instance to be tested.
+fn setup_service(runtime: ServiceRuntime<ApplicationService>) -> ApplicationService {
+    ApplicationService::new(runtime).blocking_wait()
+}
+
+/// Creates a [`ApplicationService`] instance at `now`, with the Atoma `nodes` stored in its
+/// state.
+///
+/// Each node is stored with its statistics, and is added to the sets of active or suspended
+/// nodes according to its flags. Later duplicates of a node override earlier ones.
+fn setup_service_with_nodes(
+    now: Timestamp,
+    nodes: &[(PublicKey, NodeStats, bool, bool)],
+) -> ApplicationService {
+    let runtime = ServiceRuntime::new();
+    let storage = runtime.key_value_store().to_mut();
+
+    runtime.set_system_time(now);
+
+    let mut initial_state = Application::load(ViewStorageContext::new_unsafe(storage, vec![], ()))
+        .blocking_wait()
+        .expect("Failed to load state from mock storage");
+
+    for (node, stats, is_active, is_suspended) in nodes {
+        initial_state
+            .node_stats
+            .insert(node, *stats)
+            .expect("Failed to insert node statistics in initial state");
+
+        if *is_active {
+            initial_state
+                .active_atoma_nodes
+                .insert(node)
+                .expect("Failed to insert node key in initial state");
+        } else {
+            initial_state
+                .active_atoma_nodes
+                .remove(node)
+                .expect("Failed to remove node key from initial state");
+        }
+
+        if *is_suspended {
+            initial_state
+                .suspended_atoma_nodes
+                .insert(
+                    node,
+                    NodeSuspension {
+                        reason: SuspensionReason::ConflictingResponses,
+                        suspended_at: BlockTime {
+                            timestamp: Timestamp::from(0),
+                            block_height: BlockHeight(0),
+                        },
+                    },
+                )
+                .expect("Failed to insert node suspension in initial state");
+        } else {
+            initial_state
+                .suspended_atoma_nodes
+                .remove(node)
+                .expect("Failed to remove node suspension from initial state");
+        }
+    }
+
+    initial_state
+        .save()
+        .blocking_wait()
+        .expect("Failed to save initial state to mock storage");
+
+    setup_service(runtime)
+}
+
+/// Returns a strategy to generate arbitrary [`NodeStats`].
+fn node_stats() -> impl Strategy<Value = NodeStats> {
+    (
+        (any::<u64>(), any::<u64>(), 0..5_u64),
+        proptest::option::of(any::<u64>().prop_map(Timestamp::from)),
+        any::<u64>(),
+        0..=u128::from(u64::MAX),
+        0..=u128::from(u64::MAX),
+    )
+        .prop_map(
+            |(
+                (verified_interactions, rejected_interactions, reports),
+                active_since,
+                tokens_served,
+                unclaimed_rewards_attos,
+                claimed_rewards_attos,
+            )| NodeStats {
+                verified_interactions,
+                rejected_interactions,
+                reports,
+                active_since,
+                tokens_served,
+                unclaimed_rewards: Amount::from_attos(unclaimed_rewards_attos),
+                claimed_rewards: Amount::from_attos(claimed_rewards_attos),
+            },
+        )
+}
+
+/// Seals an API `token` to the `api_token_key`, as a client does before storing it on chain.
+fn seal_api_token(api_token_key: &crypto_box::PublicKey, name: String, token: &str) -> ApiToken {
+    let ciphertext = api_token_key
+        .seal(&mut ChaCha20Rng::from_seed([0; 32]), token.as_bytes())
+        .expect("Failed to seal API token");
+
+    ApiToken {
+        name,
+        key: api_token_key.to_bytes(),
+        ciphertext,
+    }
+}
+
+/// Creates a [`ApplicationService`] instance with the `api_tokens` stored in its state.
+fn setup_service_with_api_tokens(api_tokens: &[ApiToken]) -> ApplicationService {
+    let runtime = ServiceRuntime::new();
+    let storage = runtime.key_value_store().to_mut();
+
+    let mut initial_state = Application::load(ViewStorageContext::new_unsafe(storage, vec![], ()))
+        .blocking_wait()
+        .expect("Failed to load state from mock storage");
+
+    for api_token in api_tokens.iter().cloned() {
+        initial_state
+            .api_tokens
+            .insert(&api_token.name.clone(), api_token)
+            .expect("Failed to store API token");
+    }
+
+    initial_state
+        .save()
+        .blocking_wait()
+        .expect("Failed to save initial state to mock storage");
+
+    setup_service(runtime)
+}
+
+/// Creates a [`ApplicationService`] instance with the `interactions` in its chat log.
+fn setup_service_with_chat_log(interactions: &[LoggedChatInteraction]) -> ApplicationService {
+    let entries = interactions
+        .iter()
+        .cloned()
+        .map(ChatLogEntry::Logged)
+        .collect::<Vec<_>>();
+
+    setup_service_with_chat_log_entries(&entries)
+}
+
+/// Creates an [`ApplicationService`] instance with the provided chat log `entries`, which may
+/// include redacted entries.
+fn setup_service_with_chat_log_entries(entries: &[ChatLogEntry]) -> ApplicationService {
+    setup_service_with_pruned_chat_log(entries, 0)
+}
+
+/// Creates an [`ApplicationService`] instance with the provided chat log `entries`, after the
+/// oldest `pruned_count` of them were pruned.
+fn setup_service_with_pruned_chat_log(
+    entries: &[ChatLogEntry],
+    pruned_count: usize,
+) -> ApplicationService {
+    let runtime = ServiceRuntime::new();
+    let storage = runtime.key_value_store().to_mut();
+
+    let mut initial_state = Application::load(ViewStorageContext::new_unsafe(storage, vec![], ()))
+        .blocking_wait()
+        .expect("Failed to load state from mock storage");
+
+    for (index, entry) in entries.iter().cloned().enumerate() {
+        let new_nodes = initial_state
+            .chat_log_merkle_range
+            .get_mut()
+            .push(entry.hash());
+
+        for (position, hash) in new_nodes {
+            initial_state
+                .chat_log_merkle_nodes
+                .insert(&position, hash)
+                .expect("Failed to store a Merkle node in mock storage");
+        }
+
+        initial_state
+            .chat_log
+            .insert(&(index as u64), entry)
+            .expect("Failed to store chat log entry in mock storage");
+    }
+
+    for index in 0..pruned_count {
+        initial_state
+            .chat_log
+            .remove(&(index as u64))
+            .expect("Failed to prune chat log entry from mock storage");
+    }
+
+    initial_state.chat_log_pruned_count.set(pruned_count as u64);
+
+    initial_state
+        .save()
+        .blocking_wait()
+        .expect("Failed to save initial state to mock storage");
+
+    setup_service(runtime)
+}
+
+/// Builds the expected result of a `chat` mutation that logs the `interaction` with the
+/// plaintext `response`.
+fn expected_chat_result(response: &str, interaction: ChatInteraction) -> serde_json::Value {
+    let interaction_id = interaction.id();
+    let operation = Operation::LogChatInteraction {
+        interaction,
+        payment: Amount::ZERO,
+        receipts: vec![],
+        quorum: 0,
+    };
+
+    json!({
+        "response": response,
+        "model": DEFAULT_CHAT_MODEL,
+        "usage": null,
+        "interactionId": interaction_id,
+        "operation": BASE64.encode(
+            bcs::to_bytes(&operation).expect("`Operation` should be serializable"),
+        ),
+    })
+}
+
+/// Converts a `plaintext` into [`ChatContent`] stored with the `privacy` mode, using the `salt`
+/// for the commitment if it is not public.
+fn protect_content(plaintext: &str, privacy: PrivacyMode, salt: CryptoHash) -> ChatContent {
+    match privacy {
+        PrivacyMode::Public => ChatContent::Public(plaintext.to_owned()),
+        PrivacyMode::Hashed => ChatContent::Hashed(ChatContent::commit_salted(plaintext, salt)),
+        PrivacyMode::Encrypted => ChatContent::Encrypted {
+            ciphertext: plaintext.as_bytes().to_vec(),
+            commitment: ChatContent::commit_salted(plaintext, salt),
+        },
+    }
+}
+
+/// Parses a [`ChatContent`] from its GraphQL representation.
+fn parse_chat_content(value: &async_graphql::Value) -> ChatContent {
+    let async_graphql::Value::Object(content) = value else {
+        panic!("Unexpected chat content type");
+    };
+    let async_graphql::Value::Enum(ref privacy) = content["privacy"] else {
+        panic!("Unexpected chat content privacy type");
+    };
+    let async_graphql::Value::String(ref commitment) = content["commitment"] else {
+        panic!("Unexpected chat content commitment type");
+    };
+    let commitment = CryptoHash::from_str(commitment).expect("Invalid chat content commitment");
+
+    match privacy.as_str() {
+        "PUBLIC" => {
+            let async_graphql::Value::String(ref text) = content["text"] else {
+                panic!("Unexpected chat content text type");
+            };
+            assert_eq!(commitment, ChatContent::commit(text));
+            ChatContent::Public(text.clone())
+        }
+        "HASHED" => ChatContent::Hashed(commitment),
+        "ENCRYPTED" => ChatContent::Encrypted {
+            ciphertext: parse_bytes(&content["ciphertext"]),
+            commitment,
+        },
+        _ => panic!("Unexpected chat content privacy mode"),
+    }
+}
+
+/// Parses a [`ToolCall`] from its GraphQL representation.
+fn parse_tool_call(value: &async_graphql::Value) -> ToolCall {
+    let async_graphql::Value::Object(tool_call) = value else {
+        panic!("Unexpected tool call type");
+    };
+    let async_graphql::Value::String(ref id) = tool_call["id"] else {
+        panic!("Unexpected tool call ID type");
+    };
+    let async_graphql::Value::String(ref name) = tool_call["name"] else {
+        panic!("Unexpected tool call name type");
+    };
+
+    ToolCall {
+        id: id.clone(),
+        name: name.clone(),
+        arguments: parse_chat_content(&tool_call["arguments"]),
+    }
+}
+
+/// Executes a `chat` mutation that seals the `prompt` and the mocked `response` to the
+/// `encryption_key` using the `encryption_seed`, returning the interaction to be logged and the
+/// salts of the commitments to the prompt and the response.
+fn seal_chat_interaction(
+    encryption_key: crypto_box::PublicKey,
+    encryption_seed: [u8; 32],
+    prompt: &str,
+    response: &str,
+) -> (ChatInteraction, [CryptoHash; 2]) {
+    let encryption_key = encryption_key.to_bytes();
+    let mut service = setup_service(ServiceRuntime::new());
+
+    let request = async_graphql::Request::new(format!(
+        "mutation {{ \
+            chat(\
+                apiToken: \"token\", \
+                message: {{ content: {prompt:?}, role: \"user\" }}, \
+                privacy: ENCRYPTED, \
+                encryptionKey: {encryption_key:?}, \
+                encryptionSeed: {encryption_seed:?}\
+            ) {{ operation, promptSalt, responseSalt }} \
+        }}"
+    ));
+
+    let mock_response = json!({ "choices": [{ "message": { "content": response, "role": "" } }] });
+
+    Arc::get_mut(&mut service.runtime)
+        .expect("`ServiceRuntime` should not be shared before configuring expected HTTP requests")
+        .add_expected_http_request(
+            http::Request::post(
+                format!("{ATOMA_CLOUD_URL}/v1/chat/completions"),
+                format!(
+                    "{{\
+                        \"stream\":false,\
+                        \"messages\":[{{\"content\":{prompt:?},\"role\":\"user\"}}],\
+                        \"model\":\"meta-llama/Llama-3.3-70B-Instruct\",\
+                        \"max_tokens\":128\
+                    }}"
+                ),
+            )
+            .with_header("Content-Type", b"application/json")
+            .with_header("Authorization", b"Bearer token"),
+            http::Response::ok(mock_response.to_string()),
+        );
+
+    let graphql_response = service.handle_query(request).blocking_wait();
+
+    let async_graphql::Value::Object(ref response_data) = graphql_response.data else {
+        panic!("Unexpected response data type: {graphql_response:?}");
+    };
+    let async_graphql::Value::Object(ref chat_result) = response_data["chat"] else {
+        panic!("Unexpected chat result type");
+    };
+    let async_graphql::Value::String(ref encoded_operation) = chat_result["operation"] else {
+        panic!("Unexpected operation type");
+    };
+    let operation_bytes = BASE64
+        .decode(encoded_operation)
+        .expect("Failed to decode operation");
+    let operation =
+        bcs::from_bytes::<Operation>(&operation_bytes).expect("Failed to deserialize operation");
+
+    let Operation::LogChatInteraction { interaction, .. } = operation else {
+        panic!("Unexpected operation returned from service");
+    };
+    let salts = ["promptSalt", "responseSalt"].map(|field| parse_scalar(&chat_result[field]));
+
+    (interaction, salts)
+}
+
+/// Parses a GraphQL scalar `value` using its JSON representation.
+fn parse_scalar<T: DeserializeOwned>(value: &async_graphql::Value) -> T {
+    let json = value
+        .clone()
+        .into_json()
+        .expect("Scalar should be representable as JSON");
+
+    serde_json::from_value(json).expect("Unexpected scalar representation")
+}
+
+/// Parses a [`BlockTime`] from its GraphQL `value`.
+fn parse_block_time(value: &async_graphql::Value) -> BlockTime {
+    let async_graphql::Value::Object(block_time) = value else {
+        panic!("Unexpected block time type");
+    };
+
+    BlockTime {
+        timestamp: parse_scalar(&block_time["timestamp"]),
+        block_height: parse_scalar(&block_time["blockHeight"]),
+    }
+}
+
+fn parse_bytes(value: &async_graphql::Value) -> Vec<u8> {
+    let async_graphql::Value::List(byte_list) = value else {
+        panic!("Unexpected byte list type");
+    };
+
+    byte_list
+        .iter()
+        .map(|byte_value| {
+            let async_graphql::Value::Number(byte_number) = byte_value else {
+                panic!("Unexpected byte type");
+            };
+            let byte = byte_number.as_u64().expect("Invalid value for a byte");
+
+            u8::try_from(byte).expect("Invalid integer for a byte")
+        })
+        .collect()
 }
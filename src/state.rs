@@ -1,12 +1,40 @@
 // Copyright (c) Zefchain Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
-use atoma_demo::{ChatInteraction, PublicKey};
-use linera_sdk::views::{linera_views, LogView, RootView, SetView, ViewStorageContext};
+use atoma_demo::{
+    merkle::{MerkleMountainRange, NodePosition},
+    ApiToken, AttestationPolicy, AuditLogEntry, ChatFee, ChatLogEntry, ChatLogRetention,
+    EmbeddingRecord, ImageGenerationRecord, NodeAttestation, NodeStats, NodeSuspension,
+    PrunedChatLogRange, PublicKey,
+};
+use linera_sdk::{
+    linera_base_types::CryptoHash,
+    views::{linera_views, LogView, MapView, RegisterView, RootView, SetView, ViewStorageContext},
+};
 
 #[derive(RootView, async_graphql::SimpleObject)]
 #[view(context = "ViewStorageContext")]
 pub struct Application {
     pub active_atoma_nodes: SetView<PublicKey>,
-    pub chat_log: LogView<ChatInteraction>,
+    pub suspended_atoma_nodes: MapView<PublicKey, NodeSuspension>,
+    pub node_stats: MapView<PublicKey, NodeStats>,
+    pub attestation_policy: RegisterView<Option<AttestationPolicy>>,
+    pub attested_atoma_nodes: MapView<PublicKey, NodeAttestation>,
+    pub allowed_models: SetView<String>,
+    pub chat_fee: RegisterView<Option<ChatFee>>,
+    #[graphql(skip)]
+    pub chat_log: MapView<u64, ChatLogEntry>,
+    pub chat_log_pruned_count: RegisterView<u64>,
+    pub chat_log_pruned_ranges: LogView<PrunedChatLogRange>,
+    pub chat_log_retention: RegisterView<Option<ChatLogRetention>>,
+    #[graphql(skip)]
+    pub chat_log_merkle_range: RegisterView<MerkleMountainRange>,
+    #[graphql(skip)]
+    pub chat_log_merkle_nodes: MapView<NodePosition, CryptoHash>,
+    pub is_audit_log_enabled: RegisterView<bool>,
+    pub audit_log: LogView<AuditLogEntry>,
+    pub embedding_log: LogView<EmbeddingRecord>,
+    pub image_log: LogView<ImageGenerationRecord>,
+    #[graphql(skip)]
+    pub api_tokens: MapView<String, ApiToken>,
 }
@@ -5,9 +5,11 @@
 
 use std::env;
 
-use atoma_demo::{ApplicationAbi, ChatInteraction, Operation, PublicKey};
+use atoma_demo::{ApplicationAbi, ChatContent, ChatInteraction, Operation, PublicKey};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
 use linera_sdk::{
     bcs,
+    linera_base_types::Amount,
     test::{QueryOutcome, TestValidator},
 };
 
@@ -28,44 +30,41 @@ async fn service_queries_atoma() {
                     content: \"What was the capital of Brazil in 1940\",
                     role: \"user\"
                 }}\
-            ) \
+            ) {{ response, operation }} \
         }}"
     );
 
     let QueryOutcome { response, .. } = chain.graphql_query(application_id, query).await;
 
-    let response_object = response
+    let chat_result = response["chat"]
         .as_object()
         .expect("Unexpected response from service");
 
-    let operation_list = response_object["chat"]
-        .as_array()
-        .expect("Unexpected operation representation returned from service");
-
-    let operation_bytes = operation_list
-        .iter()
-        .map(|value| {
-            let byte_integer = value
-                .as_u64()
-                .expect("Invalid byte type in serialized operation");
-
-            byte_integer
-                .try_into()
-                .expect("Invalid byte value in serialized operation")
-        })
-        .collect::<Vec<u8>>();
+    let operation_bytes = BASE64
+        .decode(
+            chat_result["operation"]
+                .as_str()
+                .expect("Unexpected operation representation returned from service"),
+        )
+        .expect("Invalid base64 encoding of serialized operation");
 
     let operation =
         bcs::from_bytes::<Operation>(&operation_bytes).expect("Failed to deserialize operation");
 
     let Operation::LogChatInteraction {
-        interaction: ChatInteraction { response, .. },
+        interaction:
+            ChatInteraction {
+                response: ChatContent::Public(response),
+                ..
+            },
+        ..
     } = operation
     else {
         panic!("Unexpected operation returned from service");
     };
 
     assert!(response.contains("Rio de Janeiro"));
+    assert_eq!(chat_result["response"].as_str(), Some(response.as_str()));
 }
 
 /// Tests if a chat interaction is verified on the creation chain and logged on the requesting
@@ -76,18 +75,27 @@ async fn chat_interaction_verification_and_logging() {
         TestValidator::with_current_application::<ApplicationAbi, _, _>((), ()).await;
 
     let fake_node = PublicKey::from([0_u8; 32]);
+    let model = "meta-llama/Llama-3.3-70B-Instruct";
     let chat_prompt = "What is one plus one?";
     let chat_response = "2";
 
     creation_chain
         .add_block(|block| {
-            block.with_operation(
-                application_id,
-                Operation::UpdateNodes {
-                    add: vec![fake_node],
-                    remove: vec![],
-                },
-            );
+            block
+                .with_operation(
+                    application_id,
+                    Operation::UpdateNodes {
+                        add: vec![fake_node],
+                        remove: vec![],
+                    },
+                )
+                .with_operation(
+                    application_id,
+                    Operation::UpdateModels {
+                        add: vec![model.to_owned()],
+                        remove: vec![],
+                    },
+                );
         })
         .await;
 
@@ -99,9 +107,15 @@ async fn chat_interaction_verification_and_logging() {
                 application_id,
                 Operation::LogChatInteraction {
                     interaction: ChatInteraction {
-                        prompt: chat_prompt.to_owned(),
-                        response: chat_response.to_owned(),
+                        model: model.to_owned(),
+                        prompt: ChatContent::Public(chat_prompt.to_owned()),
+                        response: ChatContent::Public(chat_response.to_owned()),
+                        tool_calls: vec![],
+                        choice_index: 0,
                     },
+                    payment: Amount::ZERO,
+                    receipts: vec![],
+                    quorum: 0,
                 },
             );
         })
@@ -122,10 +136,14 @@ async fn chat_interaction_verification_and_logging() {
     let QueryOutcome { response, .. } = chat_chain
         .graphql_query(
             application_id,
-            "query { chatLog { entries { prompt, response } } }",
+            "query { chatLog { entries { \
+                ... on LoggedChatInteraction { prompt { text }, requesterChainId, response { text } } \
+            } } }",
         )
         .await;
 
+    let chat_chain_id = chat_chain.id();
+
     assert_eq!(
         response.to_string(),
         format!(
@@ -133,8 +151,9 @@ async fn chat_interaction_verification_and_logging() {
             \"chatLog\":{{\
                 \"entries\":[\
                     {{\
-                        \"prompt\":{chat_prompt:?},\
-                        \"response\":{chat_response:?}\
+                        \"prompt\":{{\"text\":{chat_prompt:?}}},\
+                        \"requesterChainId\":\"{chat_chain_id}\",\
+                        \"response\":{{\"text\":{chat_response:?}}}\
                     }}\
                 ]\
             }}\